@@ -0,0 +1,50 @@
+use std::time::Instant;
+
+use merkle_trees_rs::hash::Hash;
+use merkle_trees_rs::merkle::MerkleTree;
+
+// Time to build a tree and to generate and check proofs from it, up to a
+// million leaves. With the parallel feature the default build is compared
+// against one kept on a single thread.
+fn main() {
+    for leaf_count in [1 << 10, 1 << 16, 1 << 20] {
+        let leaves: Vec<Hash> = (0..leaf_count)
+            .map(|i: usize| Hash::from_bytes(&i.to_le_bytes()))
+            .collect();
+
+        let start = Instant::now();
+        let tree = MerkleTree::new(&leaves);
+        let build_time = start.elapsed();
+        let root = tree.hash();
+
+        let start = Instant::now();
+        for i in 0..1000 {
+            let index = i * leaf_count / 1000;
+            assert!(tree
+                .proof(index)
+                .unwrap()
+                .verify(&root, &leaves[index], tree.mode()));
+        }
+        let proof_time = start.elapsed();
+
+        println!(
+            "{leaf_count:>7} leaves: built in {build_time:>10.2?}, 1000 proofs in {proof_time:>10.2?}"
+        );
+
+        #[cfg(feature = "parallel")]
+        {
+            use merkle_trees_rs::merkle::{HashMode, TreeShape};
+
+            let start = Instant::now();
+            let sequential: MerkleTree = MerkleTree::with_parallel_threshold(
+                &leaves,
+                HashMode::default(),
+                TreeShape::default(),
+                usize::MAX,
+            );
+            let sequential_time = start.elapsed();
+            assert_eq!(sequential.hash(), root);
+            println!("{leaf_count:>7} leaves: built on one thread in {sequential_time:>10.2?}");
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use std::time::Instant;
+
+use merkle_trees_rs::hash::Hash;
+use merkle_trees_rs::merkle::MerkleTree;
+
+// Compares a multiproof against one inclusion proof per leaf, in number of
+// hashes sent and time to generate and verify.
+fn main() {
+    for leaf_count in [1 << 10, 1 << 16] {
+        let leaves: Vec<Hash> = (0..leaf_count)
+            .map(|i: usize| Hash::from_bytes(&i.to_le_bytes()))
+            .collect();
+        let tree = MerkleTree::new(&leaves);
+        let root = tree.hash();
+
+        for batch in [2, 16, 128, 1024] {
+            for (layout, indices) in [
+                (
+                    "contiguous",
+                    ((leaf_count - batch) / 2..).take(batch).collect(),
+                ),
+                (
+                    "random",
+                    (0..batch)
+                        .map(|_| rand::random_range(0..leaf_count))
+                        .collect::<Vec<usize>>(),
+                ),
+            ] {
+                let start = Instant::now();
+                let proofs: Vec<_> = indices
+                    .iter()
+                    .map(|&index| tree.proof(index).unwrap())
+                    .collect();
+                assert!(proofs
+                    .iter()
+                    .zip(&indices)
+                    .all(|(proof, &index)| proof.verify(&root, &leaves[index], tree.mode())));
+                let single_time = start.elapsed();
+                let single_size: usize = proofs.iter().map(|proof| proof.steps().len()).sum();
+
+                let start = Instant::now();
+                let multiproof = tree.multiproof(&indices).unwrap();
+                let proven: Vec<&Hash> = multiproof
+                    .indices()
+                    .iter()
+                    .map(|&index| &leaves[index])
+                    .collect();
+                assert!(multiproof.verify(&root, &proven, tree.mode(), tree.shape()));
+                let multi_time = start.elapsed();
+
+                println!(
+                    "{leaf_count:>6} leaves, {batch:>4} {layout:<10}: single {single_size:>6} hashes in {single_time:>10.2?}, multi {:>5} hashes in {multi_time:>10.2?}",
+                    multiproof.hashes().len(),
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::fs::File;
+use std::io::BufWriter;
+use std::process::ExitCode;
+
+use merkle_trees_rs::manifest::{Change, Chunking, Manifest};
+
+const USAGE: &str = "usage:
+  merkle-manifest commit <path> <manifest> [--fixed SIZE | --cdc MIN AVG MAX]
+  merkle-manifest verify <path> <manifest>";
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let result = match args[..] {
+        ["commit", path, manifest, ref chunking @ ..] => match parse_chunking(chunking) {
+            Some(chunking) => commit(path, manifest, chunking),
+            None => Err(USAGE.to_string()),
+        },
+        ["verify", path, manifest] => verify(path, manifest),
+        _ => Err(USAGE.to_string()),
+    };
+    match result {
+        Ok(true) => ExitCode::SUCCESS,
+        Ok(false) => ExitCode::from(1),
+        Err(error) => {
+            eprintln!("{error}");
+            ExitCode::from(2)
+        }
+    }
+}
+
+fn parse_chunking(args: &[&str]) -> Option<Chunking> {
+    let sizes: Vec<usize> = args
+        .iter()
+        .skip(1)
+        .map(|size| size.parse().ok())
+        .collect::<Option<_>>()?;
+    let chunking = match (args.first(), &sizes[..]) {
+        (None, _) => Chunking::default(),
+        (Some(&"--fixed"), &[size]) => Chunking::Fixed(size),
+        (Some(&"--cdc"), &[min, avg, max]) => Chunking::ContentDefined { min, avg, max },
+        _ => return None,
+    };
+    chunking.is_valid().then_some(chunking)
+}
+
+fn commit(path: &str, manifest: &str, chunking: Chunking) -> Result<bool, String> {
+    let committed = Manifest::commit(path, chunking).map_err(|e| format!("{path}: {e}"))?;
+    let file = File::create(manifest).map_err(|e| format!("{manifest}: {e}"))?;
+    committed
+        .write(BufWriter::new(file))
+        .map_err(|e| format!("{manifest}: {e}"))?;
+    println!("{}", committed.hash());
+    Ok(true)
+}
+
+// Prints one line per difference and returns false if there were any.
+fn verify(path: &str, manifest: &str) -> Result<bool, String> {
+    let file = File::open(manifest).map_err(|e| format!("{manifest}: {e}"))?;
+    let manifest = Manifest::read(file).map_err(|e| format!("{manifest}: {e}"))?;
+    let differences = manifest.verify(path).map_err(|e| format!("{path}: {e}"))?;
+
+    for difference in &differences {
+        match &difference.change {
+            Change::Added => println!("added {}", difference.path),
+            Change::Removed => println!("removed {}", difference.path),
+            Change::Replaced => println!("replaced {}", difference.path),
+            Change::Modified(chunks) => {
+                let chunks: Vec<String> = chunks.iter().map(usize::to_string).collect();
+                println!(
+                    "modified {} (chunks {})",
+                    difference.path,
+                    chunks.join(", ")
+                );
+            }
+        }
+    }
+    if differences.is_empty() {
+        println!("ok {}", manifest.hash());
+    }
+    Ok(differences.is_empty())
+}
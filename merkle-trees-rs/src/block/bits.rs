@@ -1,6 +1,7 @@
 use crate::hash::Hash;
 
 #[derive(Debug, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct DifficultyTarget {
     pub exponent: u8,
     pub coefficient: u32,
@@ -21,6 +22,13 @@ impl DifficultyTarget {
         bytes
     }
 
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        Self::new(
+            bytes[3],
+            u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]),
+        )
+    }
+
     pub fn target(&self) -> [u8; 32] {
         let mut target = [0u8; 32];
         let start = (32 - self.exponent) as usize;
@@ -71,6 +79,13 @@ mod tests {
         assert_eq!(bits.to_bytes(), [0x03, 0x5d, 0x25, 0x17]);
     }
 
+    #[test]
+    fn test_reads_bytes() {
+        let bits = DifficultyTarget::from_bytes([0x03, 0x5d, 0x25, 0x17]);
+
+        assert_eq!(bits, DifficultyTarget::new(0x17, 0x255d03));
+    }
+
     #[test]
     fn test_builds_target() {
         let bits = DifficultyTarget::new(0x1d, 0xffff00);
@@ -0,0 +1,25 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BlockError {
+    MerkleRootMismatch,
+    MutatedTransactions,
+    // Every block needs at least a coinbase to have a merkle root.
+    NoTransactions,
+}
+
+impl Display for BlockError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlockError::MerkleRootMismatch => {
+                write!(f, "merkle root does not match the block transactions")
+            }
+            BlockError::MutatedTransactions => {
+                write!(f, "block transactions contain a duplicated merkle subtree")
+            }
+            BlockError::NoTransactions => write!(f, "block has no transactions"),
+        }
+    }
+}
+
+impl std::error::Error for BlockError {}
@@ -1,18 +1,21 @@
+use crate::codec::{DecodeError, Decoder};
+use crate::hasher::{DoubleSha256, Hasher};
 use crate::{block::bits::DifficultyTarget, hash::Hash, timestamp::Timestamp};
 use std::fmt::Write;
 
 #[derive(Debug, Clone, Eq)]
-pub(crate) struct BlockHeader {
-    pub(crate) version: i32,
-    pub(crate) previous_hash: Hash,
-    pub(crate) merkle_root: Hash,
-    pub(crate) timestamp: Timestamp,
-    pub(crate) difficulty_target: DifficultyTarget,
-    pub(crate) nonce: u32,
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockHeader {
+    pub version: i32,
+    pub previous_hash: Hash,
+    pub merkle_root: Hash,
+    pub timestamp: Timestamp,
+    pub difficulty_target: DifficultyTarget,
+    pub nonce: u32,
 }
 
 impl BlockHeader {
-    pub(crate) fn new(
+    pub fn new(
         version: i32,
         previous_hash: Hash,
         merkle_root: Hash,
@@ -30,7 +33,7 @@ impl BlockHeader {
         }
     }
 
-    pub(crate) fn to_bytes(&self) -> [u8; 80] {
+    pub fn to_bytes(&self) -> [u8; 80] {
         let mut bytes = [0u8; 80];
         bytes[0..4].copy_from_slice(&self.version.to_le_bytes());
         bytes[4..36].copy_from_slice(&self.previous_hash.to_bytes());
@@ -41,15 +44,38 @@ impl BlockHeader {
         bytes
     }
 
-    pub(crate) fn to_bytes_hex(&self) -> String {
+    // Fails unless `bytes` is exactly one 80-byte header.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(bytes);
+        let header = Self::decode(&mut decoder)?;
+        decoder.finish()?;
+        Ok(header)
+    }
+
+    pub(crate) fn decode(decoder: &mut Decoder) -> Result<Self, DecodeError> {
+        Ok(Self {
+            version: decoder.i32()?,
+            previous_hash: decoder.hash()?,
+            merkle_root: decoder.hash()?,
+            timestamp: Timestamp::from_bytes(decoder.array()?),
+            difficulty_target: DifficultyTarget::from_bytes(decoder.array()?),
+            nonce: decoder.u32()?,
+        })
+    }
+
+    pub fn to_bytes_hex(&self) -> String {
         self.to_bytes().iter().fold(String::new(), |mut output, b| {
             let _ = write!(output, "{b:02x}");
             output
         })
     }
 
-    pub(crate) fn hash(&self) -> Hash {
-        Hash::from_bytes(&Hash::from_bytes(&self.to_bytes()).to_bytes())
+    pub fn hash(&self) -> Hash {
+        self.hash_with::<DoubleSha256>()
+    }
+
+    pub fn hash_with<H: Hasher>(&self) -> Hash {
+        H::hash(&self.to_bytes())
     }
 }
 
@@ -62,6 +88,9 @@ impl PartialEq for BlockHeader {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::fixtures::{mainnet_block_100000_tx_ids, rpc_hash};
+    use crate::merkle::{HashMode, MerkleTree};
+    use proptest::prelude::*;
 
     fn block_header() -> BlockHeader {
         BlockHeader::new(
@@ -143,6 +172,22 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_hashes_block_header_with_hasher() {
+        use crate::hasher::Blake3;
+
+        let block_header = block_header();
+
+        assert_eq!(
+            block_header.hash(),
+            block_header.hash_with::<DoubleSha256>()
+        );
+        assert_eq!(
+            block_header.hash_with::<Blake3>(),
+            Blake3::hash(&block_header.to_bytes())
+        );
+    }
+
     #[test]
     fn test_headers_with_same_properties_are_equal() {
         let block_header1 = block_header();
@@ -173,4 +218,58 @@ mod tests {
 
         assert_ne!(block_header1, block_header2);
     }
+
+    #[test]
+    fn test_hashes_mainnet_header_with_bitcoin_merkle_root() {
+        let merkle_tree = MerkleTree::with_mode(mainnet_block_100000_tx_ids(), HashMode::Bitcoin);
+
+        // Mainnet block 100000
+        let block_header = BlockHeader::new(
+            1,
+            rpc_hash("000000000002d01c1fccc21636b607dfd930d31d01c3a62104612a1719011250"),
+            merkle_tree.hash(),
+            Some(Timestamp::new(1293623863)),
+            DifficultyTarget::new(0x1b, 0x04864c),
+            274148111,
+        );
+
+        assert_eq!(
+            block_header.hash(),
+            rpc_hash("000000000003ba27aa200b1cecaad478d2b00432346c3f1f3986da1afd33e506")
+        );
+    }
+
+    #[test]
+    fn test_reads_block_header() {
+        let block_header = block_header();
+
+        let decoded = BlockHeader::from_bytes(&block_header.to_bytes()).unwrap();
+
+        assert_eq!(decoded.version, block_header.version);
+        assert_eq!(decoded.previous_hash, block_header.previous_hash);
+        assert_eq!(decoded.merkle_root, block_header.merkle_root);
+        assert_eq!(decoded.timestamp, block_header.timestamp);
+        assert_eq!(decoded.difficulty_target, block_header.difficulty_target);
+        assert_eq!(decoded.nonce, block_header.nonce);
+    }
+
+    proptest! {
+        // Every field takes any bit pattern, so every 80 bytes are a header.
+        #[test]
+        fn test_reads_any_80_bytes_back_unchanged(bytes in prop::collection::vec(any::<u8>(), 80)) {
+            prop_assert_eq!(&BlockHeader::from_bytes(&bytes).unwrap().to_bytes()[..], &bytes[..]);
+        }
+
+        #[test]
+        fn test_rejects_headers_of_other_lengths(bytes in prop::collection::vec(any::<u8>(), 0..200)) {
+            prop_assume!(bytes.len() != 80);
+            let expected = if bytes.len() < 80 {
+                DecodeError::UnexpectedEnd
+            } else {
+                DecodeError::TrailingBytes(bytes.len() - 80)
+            };
+
+            prop_assert_eq!(BlockHeader::from_bytes(&bytes), Err(expected));
+        }
+    }
 }
@@ -1,15 +1,19 @@
 mod bits;
+mod error;
 mod header;
 
 pub use bits::DifficultyTarget;
+pub use error::BlockError;
+pub use header::BlockHeader;
 
+use crate::codec::{write_compact_size, DecodeError, Decoder};
 use crate::hash::Hash;
-use crate::merkle::MerkleTree;
+use crate::merkle::{HashMode, MerkleTree};
 use crate::timestamp::Timestamp;
 use crate::transaction::Transaction;
-use header::BlockHeader;
 
 #[derive(Debug, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Block {
     pub header: BlockHeader,
     pub transactions: Vec<Transaction>,
@@ -22,7 +26,7 @@ impl Block {
         timestamp: Option<Timestamp>,
         nonce: u32,
     ) -> Self {
-        let merkle_tree = MerkleTree::new(transactions.clone());
+        let merkle_tree = Self::merkle_tree(&transactions);
 
         let header = BlockHeader::new(
             256,
@@ -43,6 +47,52 @@ impl Block {
         self.header.hash()
     }
 
+    // The header, a CompactSize transaction count, then each transaction.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_bytes().to_vec();
+        write_compact_size(&mut bytes, self.transactions.len() as u64);
+        for transaction in &self.transactions {
+            bytes.extend(transaction.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(bytes);
+        let header = BlockHeader::decode(&mut decoder)?;
+        // Grows with what decodes rather than trusting the count.
+        let count = decoder.compact_size()?;
+        let mut transactions = Vec::new();
+        for _ in 0..count {
+            transactions.push(Transaction::decode(&mut decoder)?);
+        }
+        decoder.finish()?;
+        Ok(Self {
+            header,
+            transactions,
+        })
+    }
+
+    pub fn validate(&self) -> Result<(), BlockError> {
+        if self.transactions.is_empty() {
+            return Err(BlockError::NoTransactions);
+        }
+        let merkle_tree = Self::merkle_tree(&self.transactions);
+
+        if merkle_tree.is_mutated() {
+            return Err(BlockError::MutatedTransactions);
+        }
+        if merkle_tree.hash() != self.header.merkle_root {
+            return Err(BlockError::MerkleRootMismatch);
+        }
+        Ok(())
+    }
+
+    fn merkle_tree(transactions: &[Transaction]) -> MerkleTree {
+        // Blocks keep the legacy root so existing chains still validate.
+        MerkleTree::with_mode(transactions, HashMode::Legacy)
+    }
+
     pub fn genesis(
         transactions: Vec<Transaction>,
         timestamp: Option<Timestamp>,
@@ -61,6 +111,7 @@ impl PartialEq for Block {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
 
     fn create_test_transactions() -> Vec<Transaction> {
         vec![
@@ -106,7 +157,7 @@ mod tests {
         let genesis = Block::genesis(create_test_transactions(), Some(Timestamp::new(0)), 0);
 
         let next_block = Block::new(
-            Some(genesis.hash().clone()),
+            Some(genesis.hash()),
             vec![
                 Transaction::new(
                     1,
@@ -167,4 +218,141 @@ mod tests {
 
         assert_ne!(block, other_block);
     }
+
+    #[test]
+    fn test_validates_block() {
+        let block = Block::genesis(create_test_transactions(), Some(Timestamp::new(0)), 0);
+
+        assert_eq!(block.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_block_with_wrong_merkle_root() {
+        let mut block = Block::genesis(create_test_transactions(), Some(Timestamp::new(0)), 0);
+        block.transactions.pop();
+
+        assert_eq!(block.validate(), Err(BlockError::MerkleRootMismatch));
+    }
+
+    #[test]
+    fn test_rejects_block_with_duplicated_transactions() {
+        let mut transactions = create_test_transactions();
+        transactions.pop();
+        let block = Block::genesis(transactions.clone(), Some(Timestamp::new(0)), 0);
+
+        // Same merkle root as the original block, so the header still matches.
+        let mut mutated = block.clone();
+        mutated.transactions.push(transactions[2].clone());
+
+        assert_eq!(mutated.header.merkle_root, block.header.merkle_root);
+        assert_eq!(mutated.validate(), Err(BlockError::MutatedTransactions));
+    }
+
+    #[test]
+    fn test_reads_block() {
+        let block = Block::genesis(create_test_transactions(), Some(Timestamp::new(1)), 7);
+
+        let decoded = Block::from_bytes(&block.to_bytes()).unwrap();
+
+        assert_eq!(decoded.header.to_bytes(), block.header.to_bytes());
+        assert_eq!(decoded.transactions, block.transactions);
+        assert_eq!(decoded.validate(), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_transaction_count_past_the_end() {
+        let coinbase = Transaction::coinbase("miner".to_string(), 50, None);
+        let block = Block::genesis(vec![coinbase], None, 0);
+        let mut bytes = block.to_bytes();
+        bytes.splice(80..81, [0xfe, 0xff, 0xff, 0xff, 0xff]);
+
+        assert_eq!(Block::from_bytes(&bytes), Err(DecodeError::UnexpectedEnd));
+    }
+
+    #[test]
+    fn test_rejects_block_without_transactions() {
+        let header = Block::genesis(create_test_transactions(), None, 0).header;
+        let bytes = [&header.to_bytes()[..], &[0x00]].concat();
+
+        let block = Block::from_bytes(&bytes).unwrap();
+
+        assert!(block.transactions.is_empty());
+        assert_eq!(block.validate(), Err(BlockError::NoTransactions));
+    }
+
+    #[test]
+    fn test_rejects_non_minimal_transaction_count() {
+        let coinbase = Transaction::coinbase("miner".to_string(), 50, None);
+        let block = Block::genesis(vec![coinbase], None, 0);
+        let mut bytes = block.to_bytes();
+        assert_eq!(bytes[80], 1);
+        bytes.splice(80..81, [0xfd, 0x01, 0x00]);
+
+        assert_eq!(
+            Block::from_bytes(&bytes),
+            Err(DecodeError::NonCanonicalCompactSize)
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serializes_block() {
+        let block = Block::genesis(create_test_transactions(), Some(Timestamp::new(1)), 7);
+
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(
+            json["header"]["merkle_root"],
+            block.header.merkle_root.to_hex()
+        );
+        assert_eq!(json["header"]["timestamp"], 1);
+        assert_eq!(json["transactions"][0]["from"], "alice");
+
+        let decoded: Block = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded.header.to_bytes(), block.header.to_bytes());
+        assert_eq!(decoded.transactions, block.transactions);
+
+        let decoded: Block = postcard::from_bytes(&postcard::to_allocvec(&block).unwrap()).unwrap();
+        assert_eq!(decoded.to_bytes(), block.to_bytes());
+    }
+
+    fn arbitrary_block() -> impl Strategy<Value = Block> {
+        let transaction = (".{0,8}", ".{0,8}", any::<u64>(), any::<u32>()).prop_map(
+            |(from, to, amount, timestamp)| {
+                Transaction::new(1, from, to, amount, Some(Timestamp::new(timestamp)))
+            },
+        );
+        (
+            prop::collection::vec(transaction, 1..8),
+            any::<u32>(),
+            any::<u32>(),
+        )
+            .prop_map(|(transactions, timestamp, nonce)| {
+                Block::genesis(transactions, Some(Timestamp::new(timestamp)), nonce)
+            })
+    }
+
+    proptest! {
+        #[test]
+        fn test_reads_what_it_writes(block in arbitrary_block()) {
+            let decoded = Block::from_bytes(&block.to_bytes()).unwrap();
+
+            prop_assert_eq!(decoded.header.to_bytes(), block.header.to_bytes());
+            prop_assert_eq!(decoded.transactions, block.transactions);
+        }
+
+        #[test]
+        fn test_rejects_truncated_bytes(block in arbitrary_block(), cut in any::<prop::sample::Index>()) {
+            let bytes = block.to_bytes();
+            let len = cut.index(bytes.len());
+
+            prop_assert_eq!(Block::from_bytes(&bytes[..len]), Err(DecodeError::UnexpectedEnd));
+        }
+
+        #[test]
+        fn test_rejects_trailing_bytes(block in arbitrary_block(), extra in prop::collection::vec(any::<u8>(), 1..16)) {
+            let bytes = [block.to_bytes(), extra.clone()].concat();
+
+            prop_assert_eq!(Block::from_bytes(&bytes), Err(DecodeError::TrailingBytes(extra.len())));
+        }
+    }
 }
@@ -1,29 +1,90 @@
+use std::collections::BTreeMap;
+use std::fmt::Display;
 use std::time::{SystemTime, UNIX_EPOCH};
 
 use crate::block::Block;
 use crate::hash::Hash;
+use crate::hasher::{Hasher, Keccak256};
+use crate::merkle::{MerkleMountainRange, MmrProof};
+use crate::patricia::{rlp, PatriciaTrie};
 use crate::pow::build_block;
 use crate::timestamp::Timestamp;
 use crate::transaction::Transaction;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransferError {
+    InsufficientBalance {
+        account: String,
+        balance: u64,
+        amount: u64,
+    },
+    // The receiving balance would not fit in a u64.
+    Overflow {
+        account: String,
+    },
+}
+
+impl Display for TransferError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TransferError::InsufficientBalance {
+                account,
+                balance,
+                amount,
+            } => write!(f, "{account} holds {balance}, cannot send {amount}"),
+            TransferError::Overflow { account } => write!(f, "balance of {account} overflows"),
+        }
+    }
+}
+
+impl std::error::Error for TransferError {}
+
 pub struct Blockchain {
     blocks: Vec<Block>,
+    headers: MerkleMountainRange,
+    accounts: PatriciaTrie,
+    // The accounts before the first block.
+    genesis_accounts: PatriciaTrie,
+    state_roots: Vec<Hash>,
 }
 
 impl Blockchain {
     pub fn new() -> Self {
-        Blockchain { blocks: vec![] }
+        Self::with_balances([])
+    }
+
+    // Starts from accounts that already hold funds, like the allocation in an
+    // Ethereum genesis file. Otherwise only coinbase transactions create them.
+    pub fn with_balances(balances: impl IntoIterator<Item = (String, u64)>) -> Self {
+        let mut blockchain = Blockchain {
+            blocks: vec![],
+            headers: MerkleMountainRange::new(),
+            accounts: PatriciaTrie::new(),
+            genesis_accounts: PatriciaTrie::new(),
+            state_roots: vec![],
+        };
+        for (account, balance) in balances {
+            blockchain.set_balance(&account, balance);
+        }
+        blockchain.genesis_accounts = blockchain.accounts.clone();
+        blockchain
     }
 
-    fn add_block(&mut self, transactions: Vec<Transaction>, timestamp: Option<Timestamp>) {
+    // Rejects the whole block, leaving the chain as it was, if any of its
+    // transactions spends more than its sender holds.
+    pub fn add_block(
+        &mut self,
+        transactions: Vec<Transaction>,
+        timestamp: Option<Timestamp>,
+    ) -> Result<(), TransferError> {
         let block = match self.blocks.last() {
             None => Block::genesis(transactions, Some(Timestamp::new(0)), 0),
             Some(last_block) => Block::new(Some(last_block.hash()), transactions, timestamp, 0),
         };
-        self.blocks.push(block);
+        self.push(block)
     }
 
-    pub fn build_with_hash_rate(&mut self, hash_rate: f64) {
+    pub fn build_with_hash_rate(&mut self, hash_rate: f64) -> Result<(), TransferError> {
         let mut difficulty = 4;
         let start_time = SystemTime::now();
 
@@ -35,10 +96,8 @@ impl Blockchain {
         while self.blocks.len() < 2016 {
             let transactions = (self.blocks.len()..self.blocks.len() + 4)
                 .map(|i| {
-                    Transaction::new(
-                        1,
+                    Transaction::coinbase(
                         format!("user{}", i),
-                        format!("user{}", i + 1),
                         (i * 1000) as u64,
                         Some(Timestamp::new(i as u32)),
                     )
@@ -62,7 +121,7 @@ impl Blockchain {
                 next_block.header.nonce
             );
 
-            self.blocks.push(next_block);
+            self.push(next_block)?;
 
             let total_time = SystemTime::now()
                 .duration_since(start_time)
@@ -81,30 +140,127 @@ impl Blockchain {
                 Err(e) => print!("{}", e),
             };
         }
+        Ok(())
     }
 
-    fn replace_genesis(&mut self, transactions: Vec<Transaction>) {
+    #[cfg(test)]
+    fn replace_genesis(&mut self, transactions: Vec<Transaction>) -> Result<(), TransferError> {
         let block = Block::genesis(transactions, Some(Timestamp::new(0)), 0);
-        match self.blocks.first() {
-            None => self.blocks.push(block),
-            Some(_) => self.blocks[0] = block,
+        let mut blocks = std::mem::take(&mut self.blocks);
+        match blocks.first() {
+            None => blocks.push(block),
+            Some(_) => blocks[0] = block,
+        }
+        self.headers = MerkleMountainRange::new();
+        self.accounts = self.genesis_accounts.clone();
+        self.state_roots.clear();
+        for block in blocks {
+            self.push(block)?;
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, block: Block) -> Result<(), TransferError> {
+        // Balances are only written once every transaction is known to be
+        // valid.
+        let mut balances = BTreeMap::new();
+        for transaction in &block.transactions {
+            self.transfer(transaction, &mut balances)?;
+        }
+        for (account, balance) in balances {
+            self.set_balance(&account, balance);
+        }
+        self.state_roots.push(self.accounts.root());
+        self.headers.push(block.hash());
+        self.blocks.push(block);
+        Ok(())
+    }
+
+    // Records the balances after `transaction` in `balances`, on top of the
+    // ones already there.
+    fn transfer(
+        &self,
+        transaction: &Transaction,
+        balances: &mut BTreeMap<String, u64>,
+    ) -> Result<(), TransferError> {
+        let balance_of = |balances: &BTreeMap<String, u64>, account: &String| {
+            balances
+                .get(account)
+                .copied()
+                .unwrap_or_else(|| self.balance(account))
+        };
+        let amount = transaction.amount;
+
+        if !transaction.from.is_empty() {
+            let balance = balance_of(balances, &transaction.from);
+            let remaining =
+                balance
+                    .checked_sub(amount)
+                    .ok_or_else(|| TransferError::InsufficientBalance {
+                        account: transaction.from.clone(),
+                        balance,
+                        amount,
+                    })?;
+            balances.insert(transaction.from.clone(), remaining);
         }
+        let balance = balance_of(balances, &transaction.to);
+        let received = balance
+            .checked_add(amount)
+            .ok_or_else(|| TransferError::Overflow {
+                account: transaction.to.clone(),
+            })?;
+        balances.insert(transaction.to.clone(), received);
+        Ok(())
     }
 
-    fn hash(&self) -> Option<Hash> {
+    fn set_balance(&mut self, account: &str, balance: u64) {
+        // A zero balance encodes to an empty value, which removes the account.
+        let value = match balance {
+            0 => vec![],
+            _ => rlp::encode_u64(balance),
+        };
+        self.accounts.insert(&account_key(account), &value);
+    }
+
+    pub fn balance(&self, account: &str) -> u64 {
+        self.accounts
+            .get(&account_key(account))
+            .and_then(|value| rlp::decode(value).ok())
+            .and_then(|item| rlp::decode_u64(&item).ok())
+            .unwrap_or(0)
+    }
+
+    // Root of the account balances after the block at `height`.
+    pub fn state_root(&self, height: usize) -> Option<Hash> {
+        self.state_roots.get(height).copied()
+    }
+
+    // Proves an account balance against the latest state root, checkable with
+    // patricia::verify_proof under the account's Keccak-256 key.
+    pub fn account_proof(&self, account: &str) -> Vec<Vec<u8>> {
+        self.accounts.proof(&account_key(account))
+    }
+
+    pub fn hash(&self) -> Option<Hash> {
         self.blocks.last().map(|b| b.hash())
     }
 
-    fn verify(&self) -> bool {
+    // Commitment to every block hash so far, for light clients.
+    pub fn header_root(&self) -> Option<Hash> {
+        self.headers.root()
+    }
+
+    // Proves the block at `height` against header_root().
+    pub fn header_proof(&self, height: usize) -> Option<MmrProof> {
+        self.headers.proof(height)
+    }
+
+    pub fn verify(&self) -> bool {
         let mut previous_hash: Option<Hash> = None;
         for b in self.blocks.iter() {
             match previous_hash {
-                None => (),
-                Some(hash) => {
-                    if hash != b.header.previous_hash.clone() {
-                        return false;
-                    }
-                }
+                Some(hash) if hash != b.header.previous_hash => return false,
+                _ => (),
             }
             previous_hash = Some(b.hash());
         }
@@ -112,9 +268,22 @@ impl Blockchain {
     }
 }
 
+// Accounts are keyed by the hash of their name, like addresses in Ethereum's
+// secure state trie.
+fn account_key(account: &str) -> [u8; 32] {
+    Keccak256::hash(account.as_bytes()).to_bytes()
+}
+
+impl Default for Blockchain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::patricia::verify_proof;
 
     fn create_test_transactions(start: usize) -> Vec<Transaction> {
         (0..4)
@@ -130,6 +299,12 @@ mod tests {
             .collect()
     }
 
+    // Gives user{i} the i * 1000 that create_test_transactions has it send,
+    // for every i below `users`.
+    fn funded_blockchain(users: usize) -> Blockchain {
+        Blockchain::with_balances((0..users).map(|i| (format!("user{i}"), (i * 1000) as u64)))
+    }
+
     #[test]
     fn test_creates_blockchain() {
         let blockchain = Blockchain::new();
@@ -139,21 +314,27 @@ mod tests {
 
     #[test]
     fn test_adds_a_block() {
-        let mut blockchain = Blockchain::new();
+        let mut blockchain = funded_blockchain(4);
         let transactions = create_test_transactions(0);
         let genesis = Block::genesis(transactions.clone(), Some(Timestamp::new(0)), 0);
 
-        blockchain.add_block(transactions, Some(Timestamp::new(0)));
+        blockchain
+            .add_block(transactions, Some(Timestamp::new(0)))
+            .unwrap();
 
         assert_eq!(blockchain.hash(), Some(genesis.hash()));
     }
 
     #[test]
     fn test_adds_two_blocks() {
-        let mut blockchain = Blockchain::new();
+        let mut blockchain = funded_blockchain(8);
 
-        blockchain.add_block(create_test_transactions(0), Some(Timestamp::new(0)));
-        blockchain.add_block(create_test_transactions(4), Some(Timestamp::new(0)));
+        blockchain
+            .add_block(create_test_transactions(0), Some(Timestamp::new(0)))
+            .unwrap();
+        blockchain
+            .add_block(create_test_transactions(4), Some(Timestamp::new(0)))
+            .unwrap();
 
         assert_eq!(
             blockchain.hash().unwrap().to_hex(),
@@ -163,24 +344,181 @@ mod tests {
 
     #[test]
     fn test_verifies_chain_validity() {
-        let mut blockchain = Blockchain::new();
-
-        blockchain.add_block(create_test_transactions(0), Some(Timestamp::new(0)));
-        blockchain.add_block(create_test_transactions(4), Some(Timestamp::new(0)));
-        blockchain.add_block(create_test_transactions(8), Some(Timestamp::new(0)));
+        let mut blockchain = funded_blockchain(12);
+
+        blockchain
+            .add_block(create_test_transactions(0), Some(Timestamp::new(0)))
+            .unwrap();
+        blockchain
+            .add_block(create_test_transactions(4), Some(Timestamp::new(0)))
+            .unwrap();
+        blockchain
+            .add_block(create_test_transactions(8), Some(Timestamp::new(0)))
+            .unwrap();
 
         assert!(blockchain.verify());
     }
 
     #[test]
     fn test_does_not_verify_invalid_chain() {
+        let mut blockchain = funded_blockchain(16);
+
+        blockchain
+            .add_block(create_test_transactions(0), Some(Timestamp::new(0)))
+            .unwrap();
+        blockchain
+            .add_block(create_test_transactions(4), Some(Timestamp::new(0)))
+            .unwrap();
+        blockchain
+            .add_block(create_test_transactions(8), Some(Timestamp::new(0)))
+            .unwrap();
+        blockchain
+            .replace_genesis(create_test_transactions(12))
+            .unwrap();
+
+        assert!(!blockchain.verify());
+    }
+
+    #[test]
+    fn test_proves_block_headers() {
+        let mut blockchain = funded_blockchain(20);
+        assert_eq!(blockchain.header_root(), None);
+
+        for i in 0..5 {
+            blockchain
+                .add_block(create_test_transactions(i * 4), Some(Timestamp::new(0)))
+                .unwrap();
+        }
+        let root = blockchain.header_root().unwrap();
+
+        for (height, block) in blockchain.blocks.iter().enumerate() {
+            let proof = blockchain.header_proof(height).unwrap();
+            assert!(proof.verify(&root, &block.header.hash()));
+        }
+        assert!(blockchain.header_proof(5).is_none());
+    }
+
+    #[test]
+    fn test_header_root_follows_replaced_genesis() {
+        let mut blockchain = funded_blockchain(16);
+        blockchain
+            .add_block(create_test_transactions(0), Some(Timestamp::new(0)))
+            .unwrap();
+        blockchain
+            .add_block(create_test_transactions(4), Some(Timestamp::new(0)))
+            .unwrap();
+        let root = blockchain.header_root();
+
+        blockchain
+            .replace_genesis(create_test_transactions(12))
+            .unwrap();
+
+        assert_ne!(blockchain.header_root(), root);
+        assert!(blockchain.header_proof(0).unwrap().verify(
+            &blockchain.header_root().unwrap(),
+            &blockchain.blocks[0].hash()
+        ));
+    }
+
+    #[test]
+    fn test_tracks_account_state_root_per_block() {
+        let mut blockchain = Blockchain::new();
+        blockchain
+            .add_block(
+                vec![Transaction::coinbase("alice".to_string(), 50, None)],
+                Some(Timestamp::new(0)),
+            )
+            .unwrap();
+        blockchain
+            .add_block(
+                vec![Transaction::new(
+                    1,
+                    "alice".to_string(),
+                    "bob".to_string(),
+                    20,
+                    None,
+                )],
+                Some(Timestamp::new(0)),
+            )
+            .unwrap();
+
+        assert_eq!(blockchain.balance("alice"), 30);
+        assert_eq!(blockchain.balance("bob"), 20);
+        assert_eq!(blockchain.balance("carol"), 0);
+        assert_ne!(blockchain.state_root(0), blockchain.state_root(1));
+        assert_eq!(blockchain.state_root(2), None);
+
+        let mut expected = PatriciaTrie::new();
+        expected.insert(&account_key("alice"), &rlp::encode_u64(30));
+        expected.insert(&account_key("bob"), &rlp::encode_u64(20));
+        assert_eq!(blockchain.state_root(1), Some(expected.root()));
+    }
+
+    #[test]
+    fn test_rejects_overdraft() {
         let mut blockchain = Blockchain::new();
+        blockchain
+            .add_block(
+                vec![Transaction::coinbase("alice".to_string(), 50, None)],
+                Some(Timestamp::new(0)),
+            )
+            .unwrap();
+        let hash = blockchain.hash();
+
+        let overdraft = vec![
+            Transaction::new(1, "alice".to_string(), "bob".to_string(), 30, None),
+            Transaction::new(1, "alice".to_string(), "carol".to_string(), 30, None),
+        ];
+        assert_eq!(
+            blockchain.add_block(overdraft, Some(Timestamp::new(0))),
+            Err(TransferError::InsufficientBalance {
+                account: "alice".to_string(),
+                balance: 20,
+                amount: 30
+            })
+        );
 
-        blockchain.add_block(create_test_transactions(0), Some(Timestamp::new(0)));
-        blockchain.add_block(create_test_transactions(4), Some(Timestamp::new(0)));
-        blockchain.add_block(create_test_transactions(8), Some(Timestamp::new(0)));
-        blockchain.replace_genesis(create_test_transactions(12));
+        assert_eq!(blockchain.hash(), hash);
+        assert_eq!(blockchain.state_root(1), None);
+        assert_eq!(blockchain.balance("alice"), 50);
+        assert_eq!(blockchain.balance("bob"), 0);
+    }
 
-        assert!(!blockchain.verify());
+    #[test]
+    fn test_rejects_balance_overflow() {
+        let mut blockchain = Blockchain::with_balances([("alice".to_string(), u64::MAX)]);
+
+        assert_eq!(
+            blockchain.add_block(
+                vec![Transaction::coinbase("alice".to_string(), 1, None)],
+                Some(Timestamp::new(0)),
+            ),
+            Err(TransferError::Overflow {
+                account: "alice".to_string()
+            })
+        );
+        assert_eq!(blockchain.hash(), None);
+        assert_eq!(blockchain.balance("alice"), u64::MAX);
+    }
+
+    #[test]
+    fn test_proves_account_balances() {
+        let mut blockchain = funded_blockchain(4);
+        blockchain
+            .add_block(create_test_transactions(0), Some(Timestamp::new(0)))
+            .unwrap();
+        let root = blockchain.state_root(0).unwrap();
+
+        let proof = blockchain.account_proof("user4");
+        assert_eq!(
+            verify_proof::<Keccak256>(&root, &account_key("user4"), &proof),
+            Ok(Some(rlp::encode_u64(3000)))
+        );
+
+        let proof = blockchain.account_proof("nobody");
+        assert_eq!(
+            verify_proof::<Keccak256>(&root, &account_key("nobody"), &proof),
+            Ok(None)
+        );
     }
 }
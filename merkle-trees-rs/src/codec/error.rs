@@ -0,0 +1,33 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    UnexpectedEnd,
+    // Bytes left over after a complete value.
+    TrailingBytes(usize),
+    InvalidUtf8,
+    // A CompactSize written with more bytes than its value needs.
+    NonCanonicalCompactSize,
+    // A transaction version whose layout isn't known.
+    UnsupportedVersion(u32),
+}
+
+impl Display for DecodeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DecodeError::UnexpectedEnd => write!(f, "input ended in the middle of a value"),
+            DecodeError::TrailingBytes(count) => {
+                write!(f, "{count} bytes after the end of the value")
+            }
+            DecodeError::InvalidUtf8 => write!(f, "string is not valid UTF-8"),
+            DecodeError::NonCanonicalCompactSize => {
+                write!(f, "CompactSize is not minimally encoded")
+            }
+            DecodeError::UnsupportedVersion(version) => {
+                write!(f, "unsupported transaction version {version}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
@@ -0,0 +1,206 @@
+mod error;
+
+pub use error::DecodeError;
+
+use crate::hash::Hash;
+
+// Appends `value` as a Bitcoin CompactSize: one byte below 0xfd, otherwise
+// a 0xfd, 0xfe or 0xff marker followed by a 2, 4 or 8 byte LE integer.
+pub(crate) fn write_compact_size(bytes: &mut Vec<u8>, value: u64) {
+    match value {
+        0..0xfd => bytes.push(value as u8),
+        0xfd..=0xffff => {
+            bytes.push(0xfd);
+            bytes.extend((value as u16).to_le_bytes());
+        }
+        0x1_0000..=0xffff_ffff => {
+            bytes.push(0xfe);
+            bytes.extend((value as u32).to_le_bytes());
+        }
+        _ => {
+            bytes.push(0xff);
+            bytes.extend(value.to_le_bytes());
+        }
+    }
+}
+
+// Reads the little-endian fields the wire types are written with, failing
+// instead of panicking when the input runs out.
+pub(crate) struct Decoder<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    pub(crate) fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+
+    pub(crate) fn bytes(&mut self, len: usize) -> Result<&'a [u8], DecodeError> {
+        if len > self.bytes.len() {
+            return Err(DecodeError::UnexpectedEnd);
+        }
+        let (bytes, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Ok(bytes)
+    }
+
+    pub(crate) fn array<const N: usize>(&mut self) -> Result<[u8; N], DecodeError> {
+        Ok(self.bytes(N)?.try_into().unwrap())
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, DecodeError> {
+        Ok(u32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn i32(&mut self) -> Result<i32, DecodeError> {
+        Ok(i32::from_le_bytes(self.array()?))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, DecodeError> {
+        Ok(u64::from_le_bytes(self.array()?))
+    }
+
+    // Only the shortest encoding of each value is accepted, so every value
+    // has exactly one encoding.
+    pub(crate) fn compact_size(&mut self) -> Result<u64, DecodeError> {
+        let (value, min) = match self.array::<1>()?[0] {
+            0xfd => (u16::from_le_bytes(self.array()?) as u64, 0xfd),
+            0xfe => (u32::from_le_bytes(self.array()?) as u64, 0x1_0000),
+            0xff => (u64::from_le_bytes(self.array()?), 0x1_0000_0000),
+            byte => return Ok(byte as u64),
+        };
+        if value < min {
+            return Err(DecodeError::NonCanonicalCompactSize);
+        }
+        Ok(value)
+    }
+
+    // A CompactSize length, which can't be more than what is left.
+    pub(crate) fn length(&mut self) -> Result<usize, DecodeError> {
+        let length = self.compact_size()?;
+        match usize::try_from(length) {
+            Ok(length) if length <= self.bytes.len() => Ok(length),
+            _ => Err(DecodeError::UnexpectedEnd),
+        }
+    }
+
+    // A u64 count of items at least `min_size` bytes each, checked against
+    // what is left so a bad count can't make us allocate.
+    pub(crate) fn count(&mut self, min_size: usize) -> Result<usize, DecodeError> {
+        let count = self.u64()?;
+        match usize::try_from(count) {
+            Ok(count) if count <= self.bytes.len() / min_size => Ok(count),
+            _ => Err(DecodeError::UnexpectedEnd),
+        }
+    }
+
+    pub(crate) fn hash(&mut self) -> Result<Hash, DecodeError> {
+        Ok(Hash::new(self.array()?))
+    }
+
+    pub(crate) fn string(&mut self, len: usize) -> Result<String, DecodeError> {
+        let bytes = self.bytes(len)?;
+        String::from_utf8(bytes.to_vec()).map_err(|_| DecodeError::InvalidUtf8)
+    }
+
+    pub(crate) fn finish(self) -> Result<(), DecodeError> {
+        match self.bytes.len() {
+            0 => Ok(()),
+            count => Err(DecodeError::TrailingBytes(count)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_reads_fields_in_order() {
+        let bytes = [&7u32.to_le_bytes()[..], &(-2i32).to_le_bytes(), b"abc"].concat();
+        let mut decoder = Decoder::new(&bytes);
+
+        assert_eq!(decoder.u32(), Ok(7));
+        assert_eq!(decoder.i32(), Ok(-2));
+        assert_eq!(decoder.string(3), Ok("abc".to_string()));
+        assert_eq!(decoder.finish(), Ok(()));
+    }
+
+    #[test]
+    fn test_rejects_short_input_and_leftovers() {
+        let mut decoder = Decoder::new(&[1, 2, 3]);
+        assert_eq!(decoder.u32(), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(decoder.bytes(usize::MAX), Err(DecodeError::UnexpectedEnd));
+        assert_eq!(decoder.finish(), Err(DecodeError::TrailingBytes(3)));
+
+        assert_eq!(
+            Decoder::new(&[0xff]).string(1),
+            Err(DecodeError::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_reads_counts_that_fit_in_what_is_left() {
+        let bytes = [&2u64.to_le_bytes()[..], &[7, 8]].concat();
+        let mut decoder = Decoder::new(&bytes);
+        assert_eq!(decoder.count(1), Ok(2));
+        assert_eq!(decoder.u8(), Ok(7));
+
+        assert_eq!(
+            Decoder::new(&bytes).count(2),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Decoder::new(&u64::MAX.to_le_bytes()).count(1),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+
+    #[test]
+    fn test_writes_compact_sizes() {
+        let cases: [(u64, &[u8]); 7] = [
+            (0, &[0x00]),
+            (0xfc, &[0xfc]),
+            (0xfd, &[0xfd, 0xfd, 0x00]),
+            (0xffff, &[0xfd, 0xff, 0xff]),
+            (0x1_0000, &[0xfe, 0x00, 0x00, 0x01, 0x00]),
+            (0x1_0000_0000, &[0xff, 0, 0, 0, 0, 1, 0, 0, 0]),
+            (u64::MAX, &[0xff; 9]),
+        ];
+        for (value, expected) in cases {
+            let mut bytes = Vec::new();
+            write_compact_size(&mut bytes, value);
+            assert_eq!(bytes, expected, "{value:#x}");
+
+            let mut decoder = Decoder::new(&bytes);
+            assert_eq!(decoder.compact_size(), Ok(value));
+            assert_eq!(decoder.finish(), Ok(()));
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_minimal_compact_sizes() {
+        for bytes in [
+            &[0xfd, 0xfc, 0x00][..],
+            &[0xfe, 0xff, 0xff, 0x00, 0x00],
+            &[0xff, 0xff, 0xff, 0xff, 0xff, 0, 0, 0, 0],
+        ] {
+            assert_eq!(
+                Decoder::new(bytes).compact_size(),
+                Err(DecodeError::NonCanonicalCompactSize)
+            );
+        }
+        assert_eq!(
+            Decoder::new(&[0xfe, 0x00]).compact_size(),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        assert_eq!(
+            Decoder::new(&[0x02, 0x00]).length(),
+            Err(DecodeError::UnexpectedEnd)
+        );
+    }
+}
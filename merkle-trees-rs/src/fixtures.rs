@@ -0,0 +1,17 @@
+// Bitcoin mainnet data shared by the tests of several modules.
+
+use crate::hash::Hash;
+
+// Hashes as shown by Bitcoin RPC, which reverses the internal byte order.
+pub(crate) fn rpc_hash(hex: &str) -> Hash {
+    Hash::from_hex_reversed(hex).unwrap()
+}
+
+pub(crate) fn mainnet_block_100000_tx_ids() -> Vec<Hash> {
+    vec![
+        rpc_hash("8c14f0db3df150123e6f3dbbf30f8b955a8249b62ac1d1ff16284aefa3d06d87"),
+        rpc_hash("fff2525b8931402dd09222c50775608f75787bd2b87e56995a7bdd30f79702c4"),
+        rpc_hash("6359f0868171b1d194cbee1af2f16ea598ae8fad666d9b012c8ed2b79a236ec4"),
+        rpc_hash("e9a66845e05d5abc0ad04ec80f774a7e585c6e8db975962d069a522137b80c1d"),
+    ]
+}
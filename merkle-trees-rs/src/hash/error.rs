@@ -0,0 +1,23 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HexError {
+    // Length of the input in bytes; a hash is 64 hex digits.
+    InvalidLength(usize),
+    InvalidCharacter { index: usize, character: char },
+}
+
+impl Display for HexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HexError::InvalidLength(length) => {
+                write!(f, "expected 64 hex digits, got {length} bytes")
+            }
+            HexError::InvalidCharacter { index, character } => {
+                write!(f, "invalid hex character {character:?} at {index}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for HexError {}
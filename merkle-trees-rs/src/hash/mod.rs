@@ -0,0 +1,241 @@
+mod error;
+#[cfg(feature = "serde")]
+mod serialize;
+mod tagged;
+
+pub use error::HexError;
+pub use tagged::{
+    BlockHeaderTag, Domain, MerkleNodeTag, SignatureTag, Tag, Tagged, TaggedHasher, TxIdTag,
+};
+
+use sha2::{Digest, Sha256};
+use std::fmt::{Display, Write};
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Hash([u8; 32]);
+
+impl Hash {
+    pub fn new(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+
+    // SHA-256 of the string. To parse a hex string use from_hex or parse.
+    pub fn digest_str(data: &str) -> Self {
+        let hash = Sha256::digest(data);
+        Self(hash.into())
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Self {
+        let hash = Sha256::digest(bytes);
+        Self(hash.into())
+    }
+
+    pub fn double_from_bytes(bytes: &[u8]) -> Self {
+        Self::from_bytes(&Self::from_bytes(bytes).to_bytes())
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.0.iter().fold(String::new(), |mut output, b| {
+            let _ = write!(output, "{b:02x}");
+            output
+        })
+    }
+
+    // Bitcoin RPCs and block explorers show hashes with their bytes
+    // reversed, which is why block hashes there start with zeros.
+    pub fn to_hex_reversed(&self) -> String {
+        self.reversed().to_hex()
+    }
+
+    // Parses hashes in the order to_hex writes them, in either case.
+    pub fn from_hex(hex: &str) -> Result<Self, HexError> {
+        if hex.len() != 64 {
+            return Err(HexError::InvalidLength(hex.len()));
+        }
+        let mut bytes = [0u8; 32];
+        let mut digits = hex.char_indices().map(|(index, character)| {
+            character
+                .to_digit(16)
+                .map(|digit| digit as u8)
+                .ok_or(HexError::InvalidCharacter { index, character })
+        });
+        for byte in &mut bytes {
+            // A non-ASCII character makes the input run out of digits early,
+            // and it is reported before that happens.
+            let high = digits.next().unwrap()?;
+            let low = digits.next().unwrap()?;
+            *byte = (high << 4) | low;
+        }
+        Ok(Self(bytes))
+    }
+
+    pub fn from_hex_reversed(hex: &str) -> Result<Self, HexError> {
+        Ok(Self::from_hex(hex)?.reversed())
+    }
+
+    pub fn to_bytes(&self) -> [u8; 32] {
+        self.0
+    }
+
+    fn reversed(&self) -> Self {
+        let mut bytes = self.0;
+        bytes.reverse();
+        Self(bytes)
+    }
+}
+
+impl FromStr for Hash {
+    type Err = HexError;
+
+    fn from_str(hex: &str) -> Result<Self, Self::Err> {
+        Self::from_hex(hex)
+    }
+}
+
+impl From<[u8; 32]> for Hash {
+    fn from(bytes: [u8; 32]) -> Self {
+        Self(bytes)
+    }
+}
+
+impl Display for Hash {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.to_hex())
+    }
+}
+
+impl PartialEq<&str> for Hash {
+    fn eq(&self, other: &&str) -> bool {
+        self.to_hex() == *other
+    }
+}
+
+impl PartialEq<String> for Hash {
+    fn eq(&self, other: &String) -> bool {
+        self.to_hex() == *other
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes_str() {
+        let hash = Hash::digest_str("Hello, world!");
+        assert_eq!(
+            hash.to_hex(),
+            "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3"
+        );
+    }
+
+    #[test]
+    fn test_equals_string() {
+        let hash = Hash::digest_str("Hello, world!");
+        assert_eq!(
+            hash,
+            "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3"
+        );
+    }
+
+    #[test]
+    fn test_does_not_equal_wrong_hash() {
+        let hash = Hash::digest_str("Hello, world!");
+        assert_ne!(
+            hash,
+            "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd4"
+        );
+    }
+
+    #[test]
+    fn test_displays_hash() {
+        let hash = Hash::digest_str("Hello, world!");
+        assert_eq!(
+            format!("{hash}"),
+            "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3"
+        );
+    }
+
+    #[test]
+    fn test_hashes_bytes() {
+        const FRAME: [u8; 80] = [
+            0x00, 0x00, 0x00, 0x3a, 0x79, 0xf9, 0xb3, 0x11, 0x35, 0x2c, 0x48, 0x4b, 0xb6, 0x17,
+            0x20, 0xce, 0x16, 0x4d, 0x6a, 0x5c, 0xa8, 0x8a, 0x0a, 0xf4, 0x26, 0x4e, 0x01, 0x00,
+            0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xdf, 0x2d, 0xdb, 0x62, 0xb3, 0x58,
+            0x31, 0x73, 0xce, 0x87, 0x8a, 0x0a, 0x2e, 0x40, 0x77, 0x3d, 0x9f, 0x4e, 0xf4, 0x2d,
+            0x12, 0xd7, 0x36, 0x47, 0xa6, 0x20, 0xf3, 0x0e, 0xec, 0xa7, 0x46, 0xe7, 0x09, 0x8a,
+            0x80, 0x66, 0x25, 0x5d, 0x03, 0x17, 0x27, 0xf0, 0xc2, 0x09,
+        ];
+        let hash = Hash::from_bytes(&Hash::from_bytes(&FRAME).to_bytes());
+
+        assert_eq!(
+            hash.to_hex(),
+            "d2fd965841244f029e5b8ffce0536951a117cbaad65f00000000000000000000"
+        );
+    }
+
+    #[test]
+    fn test_double_hashes_bytes() {
+        let bytes = b"Hello, world!";
+
+        assert_eq!(
+            Hash::double_from_bytes(bytes),
+            Hash::from_bytes(&Hash::from_bytes(bytes).to_bytes())
+        );
+    }
+
+    #[test]
+    fn test_parses_hex() {
+        let hash = Hash::digest_str("Hello, world!");
+
+        assert_eq!(Hash::from_hex(&hash.to_hex()), Ok(hash));
+        assert_eq!(hash.to_hex().to_uppercase().parse::<Hash>(), Ok(hash));
+        assert_eq!(
+            "315f5bdb76d078c43b8ac0064e4a0164612b1fce77c869345bfc94c75894edd3".parse(),
+            Ok(hash)
+        );
+    }
+
+    #[test]
+    fn test_rejects_invalid_hex() {
+        assert_eq!(Hash::from_hex("abc"), Err(HexError::InvalidLength(3)));
+        assert_eq!(
+            Hash::from_hex(&format!("{}g", "0".repeat(63))),
+            Err(HexError::InvalidCharacter {
+                index: 63,
+                character: 'g'
+            })
+        );
+        assert_eq!(
+            Hash::from_hex(&format!("0{}", "é".repeat(31) + "0")),
+            Err(HexError::InvalidCharacter {
+                index: 1,
+                character: 'é'
+            })
+        );
+    }
+
+    #[test]
+    fn test_reverses_byte_order_for_display() {
+        let header_hash =
+            Hash::from_hex("d2fd965841244f029e5b8ffce0536951a117cbaad65f00000000000000000000")
+                .unwrap();
+        let rpc = "000000000000000000005fd6aacb17a1516953e0fc8f5b9e024f24415896fdd2";
+
+        assert_eq!(header_hash.to_hex_reversed(), rpc);
+        assert_eq!(Hash::from_hex_reversed(rpc), Ok(header_hash));
+    }
+
+    #[test]
+    fn test_orders_and_keys_by_bytes() {
+        let low = Hash::new([0; 32]);
+        let high = Hash::new([0xff; 32]);
+        let mut hashes = vec![high, low];
+        hashes.sort();
+
+        assert_eq!(hashes, vec![low, high]);
+        let set: std::collections::HashSet<Hash> = [low, high, low].into_iter().collect();
+        assert_eq!(set.len(), 2);
+    }
+}
@@ -0,0 +1,103 @@
+use std::fmt;
+
+use serde::de::{self, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::Hash;
+
+// Hex in human-readable formats such as JSON, the 32 raw bytes in binary
+// ones such as bincode or postcard.
+impl Serialize for Hash {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        if serializer.is_human_readable() {
+            serializer.serialize_str(&self.to_hex())
+        } else {
+            serializer.serialize_bytes(&self.0)
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for Hash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        if deserializer.is_human_readable() {
+            deserializer.deserialize_str(HashVisitor)
+        } else {
+            deserializer.deserialize_bytes(HashVisitor)
+        }
+    }
+}
+
+struct HashVisitor;
+
+impl<'de> Visitor<'de> for HashVisitor {
+    type Value = Hash;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a 64 digit hex string or 32 bytes")
+    }
+
+    fn visit_str<E: de::Error>(self, hex: &str) -> Result<Hash, E> {
+        Hash::from_hex(hex).map_err(E::custom)
+    }
+
+    fn visit_bytes<E: de::Error>(self, bytes: &[u8]) -> Result<Hash, E> {
+        let bytes = bytes
+            .try_into()
+            .map_err(|_| E::invalid_length(bytes.len(), &self))?;
+        Ok(Hash::new(bytes))
+    }
+
+    // Some formats hand bytes over as a sequence of numbers.
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Hash, A::Error> {
+        let mut bytes = [0u8; 32];
+        for (i, byte) in bytes.iter_mut().enumerate() {
+            *byte = seq
+                .next_element()?
+                .ok_or_else(|| de::Error::invalid_length(i, &self))?;
+        }
+        if seq.next_element::<u8>()?.is_some() {
+            return Err(de::Error::invalid_length(33, &self));
+        }
+        Ok(Hash::new(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_writes_hex_to_json() {
+        let hash = Hash::from_bytes(b"abc");
+
+        let json = serde_json::to_string(&hash).unwrap();
+
+        assert_eq!(json, format!("\"{}\"", hash.to_hex()));
+        assert_eq!(serde_json::from_str::<Hash>(&json).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_writes_raw_bytes_to_binary_formats() {
+        let hash = Hash::from_bytes(b"abc");
+
+        let bincode = bincode::serialize(&hash).unwrap();
+        assert_eq!(
+            bincode,
+            [&32u64.to_le_bytes()[..], &hash.to_bytes()].concat()
+        );
+        assert_eq!(bincode::deserialize::<Hash>(&bincode).unwrap(), hash);
+
+        let postcard = postcard::to_allocvec(&hash).unwrap();
+        assert_eq!(postcard, [&[32][..], &hash.to_bytes()].concat());
+        assert_eq!(postcard::from_bytes::<Hash>(&postcard).unwrap(), hash);
+    }
+
+    #[test]
+    fn test_rejects_malformed_hashes() {
+        assert!(serde_json::from_str::<Hash>("\"abc\"").is_err());
+        assert!(serde_json::from_str::<Hash>(&format!("\"{}\"", "zz".repeat(32))).is_err());
+        assert!(
+            bincode::deserialize::<Hash>(&[&31u64.to_le_bytes()[..], &[0; 31]].concat()).is_err()
+        );
+    }
+}
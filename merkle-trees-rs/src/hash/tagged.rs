@@ -0,0 +1,214 @@
+use sha2::{Digest, Sha256};
+use std::marker::PhantomData;
+use std::sync::OnceLock;
+
+use crate::hasher::Hasher;
+
+use super::Hash;
+
+// BIP-340 tagged hashing: SHA256(SHA256(tag) || SHA256(tag) || msg). The two
+// tag hashes fill exactly one SHA-256 block, so the state after them is
+// computed once and cloned for every message.
+#[derive(Debug, Clone)]
+pub struct TaggedHasher {
+    midstate: Sha256,
+}
+
+impl TaggedHasher {
+    pub fn new(tag: &str) -> Self {
+        let tag = Sha256::digest(tag.as_bytes());
+        let mut midstate = Sha256::new();
+        midstate.update(tag);
+        midstate.update(tag);
+        Self { midstate }
+    }
+
+    pub fn hash(&self, data: &[u8]) -> Hash {
+        let mut state = self.midstate.clone();
+        state.update(data);
+        Hash::new(state.finalize().into())
+    }
+}
+
+// The contexts this crate hashes in, each with its own tag so a hash from
+// one can never be passed off as a hash from another.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Domain {
+    TxId,
+    MerkleNode,
+    BlockHeader,
+    Signature,
+}
+
+impl Domain {
+    const ALL: [Domain; 4] = [
+        Domain::TxId,
+        Domain::MerkleNode,
+        Domain::BlockHeader,
+        Domain::Signature,
+    ];
+
+    pub fn tag(self) -> &'static str {
+        match self {
+            Domain::TxId => "MerkleTrees/TxId",
+            Domain::MerkleNode => "MerkleTrees/MerkleNode",
+            Domain::BlockHeader => "MerkleTrees/BlockHeader",
+            Domain::Signature => "MerkleTrees/Signature",
+        }
+    }
+
+    // Midstates are computed on first use and shared across threads.
+    pub fn hasher(self) -> &'static TaggedHasher {
+        static HASHERS: [OnceLock<TaggedHasher>; Domain::ALL.len()] =
+            [const { OnceLock::new() }; Domain::ALL.len()];
+        HASHERS[self as usize].get_or_init(|| TaggedHasher::new(self.tag()))
+    }
+}
+
+impl Hash {
+    pub fn tagged(domain: Domain, data: &[u8]) -> Self {
+        domain.hasher().hash(data)
+    }
+}
+
+// Names a Domain at the type level, so it can pick a Tagged hasher.
+pub trait Tag {
+    const DOMAIN: Domain;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct TxIdTag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct MerkleNodeTag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct BlockHeaderTag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SignatureTag;
+
+impl Tag for TxIdTag {
+    const DOMAIN: Domain = Domain::TxId;
+}
+
+impl Tag for MerkleNodeTag {
+    const DOMAIN: Domain = Domain::MerkleNode;
+}
+
+impl Tag for BlockHeaderTag {
+    const DOMAIN: Domain = Domain::BlockHeader;
+}
+
+impl Tag for SignatureTag {
+    const DOMAIN: Domain = Domain::Signature;
+}
+
+// The tagged hash of a domain as a Hasher, e.g.
+// `MerkleTree::<Tagged<MerkleNodeTag>>` or `tx_id_with::<Tagged<TxIdTag>>`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Tagged<T: Tag>(PhantomData<T>);
+
+impl<T: Tag> Hasher for Tagged<T> {
+    fn hash(data: &[u8]) -> Hash {
+        Hash::tagged(T::DOMAIN, data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::block::{BlockHeader, DifficultyTarget};
+    use crate::merkle::{HashMode, MerkleTree};
+    use crate::timestamp::Timestamp;
+    use crate::transaction::Transaction;
+
+    #[test]
+    fn test_hashes_bip340_tagged() {
+        // The TapLeaf hash of a script that is just OP_TRUE, with leaf
+        // version 0xc0 and the script's length prefix.
+        let hash = TaggedHasher::new("TapLeaf").hash(&[0xc0, 0x01, 0x51]);
+
+        assert_eq!(
+            hash,
+            "a85b2107f791b26a84e7586c28cec7cb61202ed3d01944d832500f363782d675"
+        );
+        assert_eq!(
+            TaggedHasher::new("BIP0340/challenge").hash(b""),
+            "c216d352f5818b7b4beacd4ae0a26fe888080823d2a598856661bcd54f1b3713"
+        );
+    }
+
+    #[test]
+    fn test_reuses_midstate_for_every_message() {
+        let hasher = TaggedHasher::new("MerkleTrees/TxId");
+        let tag = Sha256::digest(b"MerkleTrees/TxId");
+
+        for data in [&b""[..], b"abc", &[7; 200]] {
+            let expected = Hash::from_bytes(&[&tag[..], &tag[..], data].concat());
+            assert_eq!(hasher.hash(data), expected);
+        }
+        assert_eq!(
+            Hash::tagged(Domain::TxId, b"abc"),
+            "be2c9e7f0d83b2e7f484f55d9a9cce480e2d4fedce3b80df25e10ba3524fe658"
+        );
+    }
+
+    #[test]
+    fn test_separates_domains() {
+        let hashes: Vec<Hash> = Domain::ALL
+            .iter()
+            .map(|&domain| Hash::tagged(domain, b"same bytes"))
+            .collect();
+
+        for (i, hash) in hashes.iter().enumerate() {
+            assert_ne!(*hash, Hash::from_bytes(b"same bytes"));
+            assert!(hashes[i + 1..].iter().all(|other| other != hash));
+        }
+    }
+
+    #[test]
+    fn test_hashes_trees_with_a_domain() {
+        let leaves: Vec<Hash> = (0..5u8).map(|i| Hash::from_bytes(&[i])).collect();
+        let tree = MerkleTree::<Tagged<MerkleNodeTag>>::with_hasher(&leaves, HashMode::Bytes);
+
+        let node = |left: &Hash, right: &Hash| {
+            Hash::tagged(
+                Domain::MerkleNode,
+                &[left.to_bytes(), right.to_bytes()].concat(),
+            )
+        };
+        let pair = MerkleTree::<Tagged<MerkleNodeTag>>::with_hasher(&leaves[..2], HashMode::Bytes);
+        assert_eq!(pair.hash(), node(&leaves[0], &leaves[1]));
+        assert_ne!(tree.hash(), MerkleTree::new(&leaves).hash());
+
+        let proof = tree.proof(4).unwrap();
+        assert!(proof.verify(&tree.hash(), &leaves[4], tree.mode()));
+    }
+
+    #[test]
+    fn test_hashes_tx_ids_and_headers_with_a_domain() {
+        let tx = Transaction::new(1, "alice".to_string(), "bob".to_string(), 10, None);
+        let header = BlockHeader::new(
+            1,
+            Hash::default(),
+            tx.tx_id(),
+            Some(Timestamp::new(0)),
+            DifficultyTarget::new(0x1d, 0xffff),
+            0,
+        );
+
+        assert_eq!(
+            tx.tx_id_with::<Tagged<TxIdTag>>(),
+            Hash::tagged(Domain::TxId, &tx.to_bytes())
+        );
+        assert_eq!(
+            header.hash_with::<Tagged<BlockHeaderTag>>(),
+            Hash::tagged(Domain::BlockHeader, &header.to_bytes())
+        );
+        assert_ne!(
+            tx.tx_id_with::<Tagged<TxIdTag>>(),
+            tx.tx_id_with::<Tagged<SignatureTag>>()
+        );
+    }
+}
@@ -0,0 +1,111 @@
+use sha2::Digest;
+
+use crate::hash::Hash;
+
+// A 256-bit hash function. Trees, block headers and transaction ids take one
+// as a type parameter, so the same code can run a Bitcoin, Ethereum or BLAKE3
+// style chain.
+pub trait Hasher {
+    fn hash(data: &[u8]) -> Hash;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sha256;
+
+impl Hasher for Sha256 {
+    fn hash(data: &[u8]) -> Hash {
+        Hash::new(sha2::Sha256::digest(data).into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct DoubleSha256;
+
+impl Hasher for DoubleSha256 {
+    fn hash(data: &[u8]) -> Hash {
+        Sha256::hash(&Sha256::hash(data).to_bytes())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Sha3_256;
+
+impl Hasher for Sha3_256 {
+    fn hash(data: &[u8]) -> Hash {
+        Hash::new(sha3::Sha3_256::digest(data).into())
+    }
+}
+
+// The original Keccak padding used by Ethereum, not the standardised SHA3-256.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Keccak256;
+
+impl Hasher for Keccak256 {
+    fn hash(data: &[u8]) -> Hash {
+        Hash::new(sha3::Keccak256::digest(data).into())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Blake3;
+
+impl Hasher for Blake3 {
+    fn hash(data: &[u8]) -> Hash {
+        Hash::new(blake3::hash(data).into())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hashes_sha256() {
+        assert_eq!(
+            Sha256::hash(b"abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert_eq!(Sha256::hash(b"abc"), Hash::from_bytes(b"abc"));
+    }
+
+    #[test]
+    fn test_hashes_double_sha256() {
+        assert_eq!(
+            DoubleSha256::hash(b"abc"),
+            "4f8b42c22dd3729b519ba6f68d2da7cc5b2d606d05daed5ad5128cc03e6c6358"
+        );
+        assert_eq!(DoubleSha256::hash(b"abc"), Hash::double_from_bytes(b"abc"));
+    }
+
+    #[test]
+    fn test_hashes_sha3_256() {
+        assert_eq!(
+            Sha3_256::hash(b"abc"),
+            "3a985da74fe225b2045c172d6bd390bd855f086e3e9d525b46bfe24511431532"
+        );
+    }
+
+    #[test]
+    fn test_hashes_keccak256() {
+        assert_eq!(
+            Keccak256::hash(b""),
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470"
+        );
+        assert_eq!(
+            Keccak256::hash(b"abc"),
+            "4e03657aea45a94fc7d47ba826c8d667c0d1e6e33a64a036ec44f58fa12d6c45"
+        );
+    }
+
+    #[test]
+    fn test_hashes_blake3() {
+        assert_eq!(
+            Blake3::hash(b""),
+            "af1349b9f5f9a1a6a0404dea36dcc9499bcb25c9adc112b7cc9a93cae41f3262"
+        );
+        assert_eq!(
+            Blake3::hash(b"abc"),
+            "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"
+        );
+    }
+}
@@ -0,0 +1,14 @@
+pub mod block;
+pub mod blockchain;
+pub mod codec;
+#[cfg(test)]
+mod fixtures;
+pub mod hash;
+pub mod hasher;
+pub mod manifest;
+pub mod merkle;
+pub mod patricia;
+pub mod pos;
+pub mod pow;
+pub mod timestamp;
+pub mod transaction;
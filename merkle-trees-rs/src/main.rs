@@ -1,13 +1,6 @@
-mod block;
-mod blockchain;
-mod hash;
-mod merkle;
-mod pos;
-mod pow;
-mod timestamp;
-mod transaction;
+use merkle_trees_rs::blockchain::{Blockchain, TransferError};
 
-fn main() {
-    let mut blockchain = blockchain::Blockchain::new();
-    blockchain.build_with_hash_rate(60.0);
+fn main() -> Result<(), TransferError> {
+    let mut blockchain = Blockchain::new();
+    blockchain.build_with_hash_rate(60.0)
 }
@@ -0,0 +1,225 @@
+use std::fmt::Display;
+use std::io::{self, Read};
+
+use crate::hash::Hash;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Chunking {
+    Fixed(usize),
+    // Cuts where a rolling hash of the content matches, so an insertion only
+    // changes the chunks around it. `avg` must be a power of two.
+    ContentDefined { min: usize, avg: usize, max: usize },
+}
+
+impl Default for Chunking {
+    fn default() -> Self {
+        Chunking::ContentDefined {
+            min: 2 * 1024,
+            avg: 8 * 1024,
+            max: 64 * 1024,
+        }
+    }
+}
+
+impl Chunking {
+    pub fn is_valid(&self) -> bool {
+        match *self {
+            Chunking::Fixed(size) => size > 0,
+            Chunking::ContentDefined { min, avg, max } => {
+                0 < min && min <= avg && avg <= max && avg.is_power_of_two()
+            }
+        }
+    }
+
+    // SHA-256 of every chunk. Empty input is a single empty chunk, so every
+    // file has at least one leaf.
+    pub fn chunk_hashes<R: Read>(&self, reader: R) -> io::Result<Vec<Hash>> {
+        let mut hashes = Vec::new();
+        self.for_each_chunk(reader, |chunk| hashes.push(Hash::from_bytes(chunk)))?;
+        Ok(hashes)
+    }
+
+    fn for_each_chunk<R: Read>(&self, mut reader: R, mut f: impl FnMut(&[u8])) -> io::Result<()> {
+        if !self.is_valid() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid chunking {self}"),
+            ));
+        }
+
+        let (max, mut boundary) = match *self {
+            Chunking::Fixed(size) => (size, Boundary::Fixed),
+            Chunking::ContentDefined { min, avg, max } => (
+                max,
+                Boundary::Gear {
+                    min,
+                    mask: avg as u64 - 1,
+                    hash: 0,
+                },
+            ),
+        };
+
+        let mut chunks = 0;
+        let mut chunk = Vec::with_capacity(max);
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = match reader.read(&mut buffer) {
+                Ok(0) => break,
+                Ok(read) => read,
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(e),
+            };
+            for &byte in &buffer[..read] {
+                chunk.push(byte);
+                if chunk.len() == max || boundary.cut(byte, chunk.len()) {
+                    f(&chunk);
+                    chunks += 1;
+                    chunk.clear();
+                    boundary.reset();
+                }
+            }
+        }
+        if !chunk.is_empty() || chunks == 0 {
+            f(&chunk);
+        }
+        Ok(())
+    }
+}
+
+impl Display for Chunking {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Chunking::Fixed(size) => write!(f, "fixed {size}"),
+            Chunking::ContentDefined { min, avg, max } => write!(f, "cdc {min} {avg} {max}"),
+        }
+    }
+}
+
+enum Boundary {
+    Fixed,
+    Gear { min: usize, mask: u64, hash: u64 },
+}
+
+impl Boundary {
+    fn cut(&mut self, byte: u8, len: usize) -> bool {
+        match self {
+            Boundary::Fixed => false,
+            Boundary::Gear { min, mask, hash } => {
+                *hash = (*hash << 1).wrapping_add(GEAR[byte as usize]);
+                len >= *min && *hash & *mask == 0
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        if let Boundary::Gear { hash, .. } = self {
+            *hash = 0;
+        }
+    }
+}
+
+// One random 64-bit value per byte for the gear rolling hash, from SplitMix64
+// so chunk boundaries never change between builds.
+const GEAR: [u64; 256] = {
+    let mut table = [0u64; 256];
+    let mut state = 0u64;
+    let mut i = 0;
+    while i < 256 {
+        state = state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        table[i] = z ^ (z >> 31);
+        i += 1;
+    }
+    table
+};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic pseudo-random bytes, so content-defined cuts happen.
+    fn data(len: usize) -> Vec<u8> {
+        let mut data: Vec<u8> = (0..len / 32 + 1)
+            .flat_map(|i: usize| Hash::from_bytes(&i.to_le_bytes()).to_bytes())
+            .collect();
+        data.truncate(len);
+        data
+    }
+
+    fn new_chunks(chunking: Chunking, before: &[u8], after: &[u8]) -> usize {
+        let before = chunking.chunk_hashes(before).unwrap();
+        let after = chunking.chunk_hashes(after).unwrap();
+        after.iter().filter(|hash| !before.contains(hash)).count()
+    }
+
+    const CDC: Chunking = Chunking::ContentDefined {
+        min: 256,
+        avg: 1024,
+        max: 4096,
+    };
+
+    #[test]
+    fn test_chunks_fixed_size() {
+        let data = data(10_000);
+
+        let hashes = Chunking::Fixed(4096).chunk_hashes(&data[..]).unwrap();
+
+        assert_eq!(
+            hashes,
+            vec![
+                Hash::from_bytes(&data[..4096]),
+                Hash::from_bytes(&data[4096..8192]),
+                Hash::from_bytes(&data[8192..]),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_input_is_one_empty_chunk() {
+        for chunking in [Chunking::Fixed(16), CDC] {
+            assert_eq!(
+                chunking.chunk_hashes(&b""[..]).unwrap(),
+                vec![Hash::from_bytes(b"")]
+            );
+        }
+    }
+
+    #[test]
+    fn test_content_defined_chunks_respect_bounds() {
+        let data = data(100_000);
+        let mut sizes = Vec::new();
+        CDC.for_each_chunk(&data[..], |chunk| sizes.push(chunk.len()))
+            .unwrap();
+
+        assert_eq!(sizes.iter().sum::<usize>(), data.len());
+        assert!(sizes.len() > 50);
+        assert!(sizes[..sizes.len() - 1]
+            .iter()
+            .all(|&size| (256..=4096).contains(&size)));
+    }
+
+    #[test]
+    fn test_insertion_only_changes_nearby_chunks() {
+        let data = data(100_000);
+        let mut edited = data.clone();
+        edited.insert(50_000, b'x');
+
+        assert!(new_chunks(CDC, &data, &edited) <= 2);
+        assert!(new_chunks(Chunking::Fixed(1024), &data, &edited) > 40);
+    }
+
+    #[test]
+    fn test_rejects_invalid_chunking() {
+        let invalid = Chunking::ContentDefined {
+            min: 10,
+            avg: 1000,
+            max: 4096,
+        };
+
+        assert!(!invalid.is_valid());
+        assert!(invalid.chunk_hashes(&b"data"[..]).is_err());
+        assert!(Chunking::Fixed(0).chunk_hashes(&b"data"[..]).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+use std::fmt::Display;
+use std::io;
+
+#[derive(Debug)]
+pub enum ManifestError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+    // The hash listed for an entry doesn't match the one computed from what
+    // the manifest lists below it.
+    HashMismatch { line: usize },
+}
+
+impl From<io::Error> for ManifestError {
+    fn from(error: io::Error) -> Self {
+        ManifestError::Io(error)
+    }
+}
+
+impl Display for ManifestError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ManifestError::Io(error) => write!(f, "{error}"),
+            ManifestError::Parse { line, message } => write!(f, "line {line}: {message}"),
+            ManifestError::HashMismatch { line } => {
+                write!(f, "line {line}: hash does not match the listed contents")
+            }
+        }
+    }
+}
+
+impl std::error::Error for ManifestError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ManifestError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
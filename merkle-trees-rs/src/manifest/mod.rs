@@ -0,0 +1,656 @@
+mod chunker;
+mod error;
+
+pub use chunker::Chunking;
+pub use error::ManifestError;
+
+use std::collections::BTreeMap;
+use std::fs::{self, File};
+use std::io::{self, BufReader, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::hash::Hash;
+use crate::merkle::MerkleTree;
+
+const HEADER: &str = "merkle-manifest 1";
+
+// A committed file or directory. A file's hash is the root of a MerkleTree
+// over its chunk hashes. A directory's is the root of a MerkleTree over one
+// record per entry, sorted by name, like a git tree. Both are RFC 6962 trees,
+// so a chunk or record can't be passed off as an interior node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    hash: Hash,
+    kind: Kind,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Kind {
+    File(Vec<Hash>),
+    Directory(BTreeMap<String, Entry>),
+}
+
+impl Entry {
+    // No chunks at all is a single empty chunk, as Chunking::chunk_hashes
+    // gives for an empty file.
+    pub fn file(mut chunks: Vec<Hash>) -> Self {
+        if chunks.is_empty() {
+            chunks.push(Hash::from_bytes(b""));
+        }
+        Self {
+            hash: Self::file_tree(&chunks).hash(),
+            kind: Kind::File(chunks),
+        }
+    }
+
+    pub fn directory(entries: BTreeMap<String, Entry>) -> Self {
+        // A record is the entry type, its name, a NUL that no name contains,
+        // and the entry's hash. An empty directory gets a single empty record
+        // so it still has a root.
+        let mut records: Vec<Vec<u8>> = entries
+            .iter()
+            .map(|(name, entry)| {
+                let kind = if entry.is_directory() { b'd' } else { b'f' };
+                [&[kind], name.as_bytes(), &[0], &entry.hash.to_bytes()].concat()
+            })
+            .collect();
+        if records.is_empty() {
+            records.push(Vec::new());
+        }
+        Self {
+            hash: MerkleTree::rfc6962(records).hash(),
+            kind: Kind::Directory(entries),
+        }
+    }
+
+    // Follows symlinks and skips anything that is neither a file nor a
+    // directory. A symlink back to a directory being committed is an error.
+    pub fn commit<P: AsRef<Path>>(path: P, chunking: Chunking) -> io::Result<Self> {
+        Self::commit_within(path.as_ref(), chunking, &mut Vec::new())
+    }
+
+    // `ancestors` holds the canonical paths of the directories above `path`.
+    fn commit_within(
+        path: &Path,
+        chunking: Chunking,
+        ancestors: &mut Vec<PathBuf>,
+    ) -> io::Result<Self> {
+        if fs::metadata(path)?.is_file() {
+            return Ok(Self::file(chunking.chunk_hashes(File::open(path)?)?));
+        }
+        let canonical = fs::canonicalize(path)?;
+        if ancestors.contains(&canonical) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("symlink cycle at {}", path.display()),
+            ));
+        }
+        ancestors.push(canonical);
+
+        let mut entries = BTreeMap::new();
+        for dir_entry in fs::read_dir(path)? {
+            let dir_entry = dir_entry?;
+            let name = dir_entry
+                .file_name()
+                .into_string()
+                .ok()
+                .filter(|name| !name.contains('\n'))
+                .ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("unsupported file name in {}", path.display()),
+                    )
+                })?;
+            let metadata = fs::metadata(dir_entry.path())?;
+            if metadata.is_file() || metadata.is_dir() {
+                let entry = Self::commit_within(&dir_entry.path(), chunking, ancestors)?;
+                entries.insert(name, entry);
+            }
+        }
+        ancestors.pop();
+        Ok(Self::directory(entries))
+    }
+
+    pub fn hash(&self) -> &Hash {
+        &self.hash
+    }
+
+    pub fn is_directory(&self) -> bool {
+        matches!(self.kind, Kind::Directory(_))
+    }
+
+    pub fn chunks(&self) -> Option<&[Hash]> {
+        match &self.kind {
+            Kind::File(chunks) => Some(chunks),
+            Kind::Directory(_) => None,
+        }
+    }
+
+    pub fn entries(&self) -> Option<&BTreeMap<String, Entry>> {
+        match &self.kind {
+            Kind::File(_) => None,
+            Kind::Directory(entries) => Some(entries),
+        }
+    }
+
+    fn file_tree(chunks: &[Hash]) -> MerkleTree {
+        MerkleTree::rfc6962(chunks)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Change {
+    Added,
+    Removed,
+    // A file became a directory or the other way round.
+    Replaced,
+    // Indices of the chunks that differ in the new file, including chunks
+    // only one of the versions has.
+    Modified(Vec<usize>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Difference {
+    pub path: String,
+    pub change: Change,
+}
+
+// Walks both trees down from the root, skipping every entry whose hash is
+// unchanged. Paths are relative to the root, which is ".".
+pub fn diff(old: &Entry, new: &Entry) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    diff_entries(".", old, new, &mut differences);
+    differences
+}
+
+fn diff_entries(path: &str, old: &Entry, new: &Entry, differences: &mut Vec<Difference>) {
+    if old.hash == new.hash {
+        return;
+    }
+    let change = match (&old.kind, &new.kind) {
+        (Kind::File(old), Kind::File(new)) => {
+            Change::Modified(Entry::file_tree(old).diff(&Entry::file_tree(new)))
+        }
+        (Kind::Directory(old), Kind::Directory(new)) => {
+            let mut names: Vec<&String> = old.keys().chain(new.keys()).collect();
+            names.sort_unstable();
+            names.dedup();
+            for name in names {
+                let path = if path == "." {
+                    name.clone()
+                } else {
+                    format!("{path}/{name}")
+                };
+                match (old.get(name), new.get(name)) {
+                    (Some(old), Some(new)) => diff_entries(&path, old, new, differences),
+                    (Some(_), None) => differences.push(Difference {
+                        path,
+                        change: Change::Removed,
+                    }),
+                    (None, _) => differences.push(Difference {
+                        path,
+                        change: Change::Added,
+                    }),
+                }
+            }
+            return;
+        }
+        _ => Change::Replaced,
+    };
+    differences.push(Difference {
+        path: path.to_string(),
+        change,
+    });
+}
+
+// The root of a committed file or directory, written as text:
+//
+//   merkle-manifest 1
+//   chunking cdc 2048 8192 65536
+//   root <hex>
+//   dir <hex> <entries> .
+//   file <hex> <chunks> <name>
+//   chunk <hex>
+//
+// Entries are listed depth first, each followed by its chunks or entries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Manifest {
+    chunking: Chunking,
+    root: Entry,
+}
+
+impl Manifest {
+    pub fn new(chunking: Chunking, root: Entry) -> Self {
+        Self { chunking, root }
+    }
+
+    pub fn commit<P: AsRef<Path>>(path: P, chunking: Chunking) -> io::Result<Self> {
+        Ok(Self::new(chunking, Entry::commit(path, chunking)?))
+    }
+
+    pub fn chunking(&self) -> Chunking {
+        self.chunking
+    }
+
+    pub fn root(&self) -> &Entry {
+        &self.root
+    }
+
+    pub fn hash(&self) -> &Hash {
+        self.root.hash()
+    }
+
+    // Commits `path` again with the same chunking and reports what changed
+    // since this manifest was written.
+    pub fn verify<P: AsRef<Path>>(&self, path: P) -> io::Result<Vec<Difference>> {
+        Ok(diff(&self.root, &Entry::commit(path, self.chunking)?))
+    }
+
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writeln!(writer, "{HEADER}")?;
+        writeln!(writer, "chunking {}", self.chunking)?;
+        writeln!(writer, "root {}", self.root.hash)?;
+        Self::write_entry(&mut writer, ".", &self.root)?;
+        writer.flush()
+    }
+
+    fn write_entry<W: Write>(writer: &mut W, name: &str, entry: &Entry) -> io::Result<()> {
+        match &entry.kind {
+            Kind::File(chunks) => {
+                writeln!(writer, "file {} {} {name}", entry.hash, chunks.len())?;
+                for chunk in chunks {
+                    writeln!(writer, "chunk {chunk}")?;
+                }
+            }
+            Kind::Directory(entries) => {
+                writeln!(writer, "dir {} {} {name}", entry.hash, entries.len())?;
+                for (name, entry) in entries {
+                    Self::write_entry(writer, name, entry)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // Every listed hash is recomputed, so a manifest that was edited by hand
+    // or truncated is rejected rather than trusted.
+    pub fn read<R: Read>(reader: R) -> Result<Self, ManifestError> {
+        let mut text = String::new();
+        BufReader::new(reader).read_to_string(&mut text)?;
+        let mut lines = Lines {
+            lines: text.lines(),
+            line: 0,
+        };
+
+        if lines.next_line()? != HEADER {
+            return Err(lines.error("not a manifest"));
+        }
+        let chunking = lines.field("chunking")?;
+        let chunking = parse_chunking(chunking).ok_or_else(|| lines.error("invalid chunking"))?;
+        let root = lines.field("root")?;
+        let root = Hash::from_hex(root).map_err(|e| lines.error(&e.to_string()))?;
+        let root_line = lines.line;
+        let (name, entry) = lines.entry()?;
+        if name != "." {
+            return Err(lines.error("root entry must be named ."));
+        }
+        if entry.hash != root {
+            return Err(ManifestError::HashMismatch { line: root_line });
+        }
+        if lines.lines.next().is_some() {
+            return Err(lines.error("unexpected line after the root entry"));
+        }
+        Ok(Self::new(chunking, entry))
+    }
+}
+
+struct Lines<'a> {
+    lines: std::str::Lines<'a>,
+    line: usize,
+}
+
+impl<'a> Lines<'a> {
+    fn next_line(&mut self) -> Result<&'a str, ManifestError> {
+        self.line += 1;
+        self.lines
+            .next()
+            .ok_or_else(|| self.error("unexpected end of manifest"))
+    }
+
+    // The rest of a `<keyword> <value>` line.
+    fn field(&mut self, keyword: &str) -> Result<&'a str, ManifestError> {
+        let line = self.next_line()?;
+        line.strip_prefix(keyword)
+            .and_then(|rest| rest.strip_prefix(' '))
+            .ok_or_else(|| self.error(&format!("expected {keyword}")))
+    }
+
+    fn entry(&mut self) -> Result<(String, Entry), ManifestError> {
+        let line = self.next_line()?;
+        let number = self.line;
+        let mut fields = line.splitn(4, ' ');
+        let kind = fields.next().unwrap_or_default();
+        let hash = fields.next().unwrap_or_default();
+        let hash = Hash::from_hex(hash).map_err(|e| self.error(&e.to_string()))?;
+        let count: usize = fields
+            .next()
+            .and_then(|count| count.parse().ok())
+            .ok_or_else(|| self.error("invalid count"))?;
+        let name = fields
+            .next()
+            .filter(|name| !name.is_empty())
+            .ok_or_else(|| self.error("missing name"))?
+            .to_string();
+
+        let entry = match kind {
+            // Even an empty file has one chunk.
+            "file" if count == 0 => return Err(self.error("invalid count")),
+            "file" => {
+                let mut chunks = Vec::new();
+                for _ in 0..count {
+                    let chunk = self.field("chunk")?;
+                    chunks.push(Hash::from_hex(chunk).map_err(|e| self.error(&e.to_string()))?);
+                }
+                Entry::file(chunks)
+            }
+            "dir" => {
+                let mut entries = BTreeMap::new();
+                for _ in 0..count {
+                    let line = self.line + 1;
+                    let (name, entry) = self.entry()?;
+                    if entries.insert(name, entry).is_some() {
+                        return Err(ManifestError::Parse {
+                            line,
+                            message: "duplicate name".to_string(),
+                        });
+                    }
+                }
+                Entry::directory(entries)
+            }
+            _ => {
+                return Err(ManifestError::Parse {
+                    line: number,
+                    message: "expected file or dir".to_string(),
+                })
+            }
+        };
+        if entry.hash != hash {
+            return Err(ManifestError::HashMismatch { line: number });
+        }
+        Ok((name, entry))
+    }
+
+    fn error(&self, message: &str) -> ManifestError {
+        ManifestError::Parse {
+            line: self.line,
+            message: message.to_string(),
+        }
+    }
+}
+
+fn parse_chunking(value: &str) -> Option<Chunking> {
+    let fields: Vec<usize> = value
+        .split(' ')
+        .skip(1)
+        .map(|field| field.parse().ok())
+        .collect::<Option<_>>()?;
+    let chunking = match (value.split(' ').next()?, &fields[..]) {
+        ("fixed", &[size]) => Chunking::Fixed(size),
+        ("cdc", &[min, avg, max]) => Chunking::ContentDefined { min, avg, max },
+        _ => return None,
+    };
+    chunking.is_valid().then_some(chunking)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A fresh directory under the system temp dir, removed when dropped.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new(name: &str) -> Self {
+            let path =
+                std::env::temp_dir().join(format!("merkle-manifest-{name}-{}", std::process::id()));
+            let _ = fs::remove_dir_all(&path);
+            fs::create_dir_all(&path).unwrap();
+            Self(path)
+        }
+
+        fn write(&self, path: &str, contents: &[u8]) {
+            let path = self.0.join(path);
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, contents).unwrap();
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    const FIXED: Chunking = Chunking::Fixed(4);
+
+    fn tree(dir: &TempDir) {
+        dir.write("README", b"hello world!");
+        dir.write("src/lib.rs", b"pub mod a;");
+        dir.write("src/a.rs", b"");
+    }
+
+    fn modified(path: &str, chunks: Vec<usize>) -> Difference {
+        Difference {
+            path: path.to_string(),
+            change: Change::Modified(chunks),
+        }
+    }
+
+    #[test]
+    fn test_commits_file_as_tree_of_chunks() {
+        let dir = TempDir::new("file");
+        dir.write("file", b"abcdefghij");
+
+        let entry = Entry::commit(dir.0.join("file"), FIXED).unwrap();
+
+        let chunks = vec![
+            Hash::from_bytes(b"abcd"),
+            Hash::from_bytes(b"efgh"),
+            Hash::from_bytes(b"ij"),
+        ];
+        assert_eq!(entry.hash(), &MerkleTree::rfc6962(&chunks).hash());
+        assert_eq!(entry.chunks(), Some(&chunks[..]));
+    }
+
+    #[test]
+    fn test_commits_directories_recursively() {
+        let dir = TempDir::new("recursive");
+        tree(&dir);
+
+        let root = Entry::commit(&dir.0, FIXED).unwrap();
+
+        let src = &root.entries().unwrap()["src"];
+        assert!(src.is_directory());
+        assert_eq!(
+            src.entries().unwrap().keys().collect::<Vec<_>>(),
+            ["a.rs", "lib.rs"]
+        );
+        let expected = Entry::directory(BTreeMap::from([
+            (
+                "README".to_string(),
+                Entry::commit(dir.0.join("README"), FIXED).unwrap(),
+            ),
+            (
+                "src".to_string(),
+                Entry::commit(dir.0.join("src"), FIXED).unwrap(),
+            ),
+        ]));
+        assert_eq!(root, expected);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_rejects_symlink_cycles() {
+        let dir = TempDir::new("cycle");
+        tree(&dir);
+        std::os::unix::fs::symlink("..", dir.0.join("src/parent")).unwrap();
+        std::os::unix::fs::symlink("src", dir.0.join("source")).unwrap();
+
+        let error = Entry::commit(&dir.0, FIXED).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+
+        fs::remove_file(dir.0.join("src/parent")).unwrap();
+        let root = Entry::commit(&dir.0, FIXED).unwrap();
+        assert_eq!(
+            root.entries().unwrap()["source"],
+            root.entries().unwrap()["src"]
+        );
+    }
+
+    #[test]
+    fn test_file_hash_cannot_be_forged_from_chunk_hashes() {
+        let dir = TempDir::new("forged");
+        let (a, b) = ([b'a'; 16], [b'b'; 16]);
+        dir.write("split", &[a, b].concat());
+        // One chunk holding the two chunk hashes, i.e. the interior node
+        // above them.
+        let node = [
+            Hash::from_bytes(&a).to_bytes(),
+            Hash::from_bytes(&b).to_bytes(),
+        ]
+        .concat();
+        dir.write("forged", &node);
+
+        let split = Entry::commit(dir.0.join("split"), Chunking::Fixed(16)).unwrap();
+        let forged = Entry::commit(dir.0.join("forged"), Chunking::Fixed(64)).unwrap();
+
+        assert_eq!(split.chunks().unwrap().len(), 2);
+        assert_ne!(split.hash(), forged.hash());
+        assert_eq!(diff(&split, &forged), vec![modified(".", vec![0, 1])]);
+    }
+
+    #[test]
+    fn test_file_without_chunks_is_one_empty_chunk() {
+        let dir = TempDir::new("empty");
+        dir.write("empty", b"");
+
+        let file = Entry::file(vec![]);
+
+        assert_eq!(file.chunks(), Some(&[Hash::from_bytes(b"")][..]));
+        assert_eq!(file, Entry::commit(dir.0.join("empty"), FIXED).unwrap());
+    }
+
+    #[test]
+    fn test_directory_hash_depends_on_names() {
+        let file = Entry::file(vec![Hash::from_bytes(b"")]);
+        let a = Entry::directory(BTreeMap::from([("a".to_string(), file.clone())]));
+        let b = Entry::directory(BTreeMap::from([("b".to_string(), file)]));
+
+        assert_ne!(a.hash(), b.hash());
+        assert_ne!(Entry::directory(BTreeMap::new()).hash(), a.hash());
+    }
+
+    #[test]
+    fn test_reads_what_it_writes() {
+        let dir = TempDir::new("roundtrip");
+        tree(&dir);
+        let manifest = Manifest::commit(&dir.0, Chunking::default()).unwrap();
+
+        let mut written = Vec::new();
+        manifest.write(&mut written).unwrap();
+        let read = Manifest::read(&written[..]).unwrap();
+
+        assert_eq!(read, manifest);
+        assert!(String::from_utf8(written).unwrap().starts_with(&format!(
+            "{HEADER}\nchunking cdc 2048 8192 65536\nroot {}\n",
+            manifest.hash()
+        )));
+    }
+
+    #[test]
+    fn test_rejects_tampered_manifest() {
+        let manifest = Manifest::new(
+            FIXED,
+            Entry::directory(BTreeMap::from([(
+                "file".to_string(),
+                Entry::file(vec![Hash::from_bytes(b"abcd")]),
+            )])),
+        );
+        let mut written = Vec::new();
+        manifest.write(&mut written).unwrap();
+        let written = String::from_utf8(written).unwrap();
+
+        let chunk = Hash::from_bytes(b"abcd").to_hex();
+        let tampered = written.replace(
+            &format!("chunk {chunk}"),
+            &format!("chunk {}", Hash::from_bytes(b"dcba")),
+        );
+        assert!(matches!(
+            Manifest::read(tampered.as_bytes()),
+            Err(ManifestError::HashMismatch { line: 5 })
+        ));
+
+        let truncated = &written[..written.len() - 1 - chunk.len() - 7];
+        assert!(matches!(
+            Manifest::read(truncated.as_bytes()),
+            Err(ManifestError::Parse { line: 6, .. })
+        ));
+        assert!(matches!(
+            Manifest::read(written.replace("fixed 4", "fixed 0").as_bytes()),
+            Err(ManifestError::Parse { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_rejects_file_without_chunks() {
+        let hash = Hash::default();
+        let manifest = format!("{HEADER}\nchunking fixed 4\nroot {hash}\nfile {hash} 0 .\n");
+
+        assert!(matches!(
+            Manifest::read(manifest.as_bytes()),
+            Err(ManifestError::Parse { line: 4, .. })
+        ));
+    }
+
+    #[test]
+    fn test_verifies_unchanged_tree() {
+        let dir = TempDir::new("unchanged");
+        tree(&dir);
+        let manifest = Manifest::commit(&dir.0, FIXED).unwrap();
+
+        assert!(manifest.verify(&dir.0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_reports_changed_chunks_and_files() {
+        let dir = TempDir::new("changed");
+        tree(&dir);
+        dir.write("docs/guide", b"guide");
+        let manifest = Manifest::commit(&dir.0, FIXED).unwrap();
+
+        dir.write("README", b"hello World!!");
+        dir.write("src/b.rs", b"");
+        fs::remove_file(dir.0.join("src/a.rs")).unwrap();
+        fs::remove_file(dir.0.join("docs/guide")).unwrap();
+        dir.write("docs/guide/index", b"guide");
+
+        assert_eq!(
+            manifest.verify(&dir.0).unwrap(),
+            vec![
+                modified("README", vec![1, 3]),
+                Difference {
+                    path: "docs/guide".to_string(),
+                    change: Change::Replaced,
+                },
+                Difference {
+                    path: "src/a.rs".to_string(),
+                    change: Change::Removed,
+                },
+                Difference {
+                    path: "src/b.rs".to_string(),
+                    change: Change::Added,
+                },
+            ]
+        );
+    }
+}
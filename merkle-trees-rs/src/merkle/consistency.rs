@@ -0,0 +1,226 @@
+use std::marker::PhantomData;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
+
+use super::HashMode;
+
+// Proof that a tree of `old_size` leaves is a prefix of a tree of `new_size`
+// leaves, for trees with the RFC 6962 shape.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConsistencyProof<H: Hasher = Sha256> {
+    old_size: usize,
+    new_size: usize,
+    hashes: Vec<Hash>,
+    mode: HashMode,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> ConsistencyProof<H> {
+    pub fn new(old_size: usize, new_size: usize, hashes: Vec<Hash>, mode: HashMode) -> Self {
+        Self {
+            old_size,
+            new_size,
+            hashes,
+            mode,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn old_size(&self) -> usize {
+        self.old_size
+    }
+
+    pub fn new_size(&self) -> usize {
+        self.new_size
+    }
+
+    pub fn hashes(&self) -> &[Hash] {
+        &self.hashes
+    }
+
+    pub fn mode(&self) -> HashMode {
+        self.mode
+    }
+
+    // Verification algorithm from RFC 9162, section 2.1.4.2. The mode comes
+    // from the verifier, not from the proof.
+    pub fn verify(&self, old_root: &Hash, new_root: &Hash, mode: HashMode) -> bool {
+        if self.mode != mode || self.old_size == 0 || self.old_size > self.new_size {
+            return false;
+        }
+        if self.old_size == self.new_size {
+            return self.hashes.is_empty() && old_root == new_root;
+        }
+
+        let mut hashes = self.hashes.iter();
+        let mut first = self.old_size - 1;
+        let mut second = self.new_size - 1;
+
+        let start = if self.old_size.is_power_of_two() {
+            old_root
+        } else {
+            match hashes.next() {
+                Some(hash) => hash,
+                None => return false,
+            }
+        };
+        while first & 1 == 1 {
+            first >>= 1;
+            second >>= 1;
+        }
+
+        let mut old_hash = *start;
+        let mut new_hash = *start;
+        for hash in hashes {
+            if second == 0 {
+                return false;
+            }
+            if first & 1 == 1 || first == second {
+                old_hash = self.mode.hash_nodes::<H>(hash, &old_hash);
+                new_hash = self.mode.hash_nodes::<H>(hash, &new_hash);
+                while first & 1 == 0 && first != 0 {
+                    first >>= 1;
+                    second >>= 1;
+                }
+            } else {
+                new_hash = self.mode.hash_nodes::<H>(&new_hash, hash);
+            }
+            first >>= 1;
+            second >>= 1;
+        }
+
+        old_hash == *old_root && new_hash == *new_root && second == 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{test_leaves as leaves, MerkleTree};
+
+    #[test]
+    fn test_creates_rfc9162_consistency_proofs() {
+        // Certificate Transparency reference vectors over its 8 test leaves.
+        let leaves: Vec<Vec<u8>> = vec![
+            vec![],
+            vec![0x00],
+            vec![0x10],
+            vec![0x20, 0x21],
+            vec![0x30, 0x31],
+            vec![0x40, 0x41, 0x42, 0x43],
+            vec![0x50, 0x51, 0x52, 0x53, 0x54, 0x55, 0x56, 0x57],
+            (0x60..=0x6f).collect(),
+        ];
+        let cases: [(usize, usize, Vec<&str>); 4] = [
+            (1, 1, vec![]),
+            (
+                1,
+                8,
+                vec![
+                    "96a296d224f285c67bee93c30f8a309157f0daa35dc5b87e410b78630a09cfc7",
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "6b47aaf29ee3c2af9af889bc1fb9254dabd31177f16232dd6aab035ca39bf6e4",
+                ],
+            ),
+            (
+                6,
+                8,
+                vec![
+                    "0ebc5d3437fbe2db158b9f126a1d118e308181031d0a949f8dededebc558ef6a",
+                    "ca854ea128ed050b41b35ffc1b87b8eb2bde461e9e3b5596ece6b9d5975a0ae0",
+                    "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+                ],
+            ),
+            (
+                2,
+                5,
+                vec![
+                    "5f083f0a1a33ca076a95279832580db3e0ef4584bdff1f54c8a360f50de3031e",
+                    "bc1a0643b12e4d2d7c77918f44e0f4f79a838b6cf9ec5b5c283e1f4d88599e6b",
+                ],
+            ),
+        ];
+
+        for (old_size, new_size, expected) in cases {
+            let old = MerkleTree::rfc6962(&leaves[..old_size]);
+            let new = MerkleTree::rfc6962(&leaves[..new_size]);
+            let proof = new.consistency_proof(old_size).unwrap();
+
+            assert_eq!(proof.hashes().len(), expected.len());
+            for (hash, expected) in proof.hashes().iter().zip(expected) {
+                assert_eq!(*hash, expected);
+            }
+            assert!(proof.verify(&old.hash(), &new.hash(), new.mode()));
+        }
+    }
+
+    #[test]
+    fn test_verifies_every_prefix() {
+        let leaves = leaves(20);
+
+        for new_size in 1..=leaves.len() {
+            let new = MerkleTree::rfc6962(&leaves[..new_size]);
+            for old_size in 1..=new_size {
+                let old = MerkleTree::rfc6962(&leaves[..old_size]);
+                let proof = new.consistency_proof(old_size).unwrap();
+
+                assert_eq!(proof.old_size(), old_size);
+                assert_eq!(proof.new_size(), new_size);
+                assert!(
+                    proof.verify(&old.hash(), &new.hash(), new.mode()),
+                    "{old_size} -> {new_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_rejects_tree_that_is_not_a_prefix() {
+        let leaves = leaves(7);
+        let mut rewritten = leaves.clone();
+        rewritten[1] = Hash::from_bytes(b"rewritten");
+
+        let old = MerkleTree::rfc6962(&rewritten[..3]);
+        let new = MerkleTree::rfc6962(&leaves);
+        let proof = new.consistency_proof(3).unwrap();
+
+        assert!(!proof.verify(&old.hash(), &new.hash(), new.mode()));
+    }
+
+    #[test]
+    fn test_rejects_invalid_sizes() {
+        let leaves = leaves(4);
+        let tree = MerkleTree::rfc6962(&leaves);
+        let old = MerkleTree::rfc6962(&leaves[..3]);
+        let proof = tree.consistency_proof(3).unwrap();
+        let resized = ConsistencyProof::<Sha256>::new(3, 5, proof.hashes().to_vec(), proof.mode());
+        assert!(proof.verify(&old.hash(), &tree.hash(), tree.mode()));
+        assert!(!resized.verify(&old.hash(), &tree.hash(), tree.mode()));
+
+        assert_eq!(tree.consistency_proof(0), None);
+        assert_eq!(tree.consistency_proof(5), None);
+        assert!(
+            !ConsistencyProof::<Sha256>::new(4, 2, vec![], HashMode::DomainSeparated).verify(
+                &tree.hash(),
+                &tree.hash(),
+                tree.mode()
+            )
+        );
+    }
+
+    #[test]
+    fn test_rejects_proof_with_other_mode() {
+        let leaves = leaves(7);
+        let old = MerkleTree::rfc6962(&leaves[..3]);
+        let new = MerkleTree::rfc6962(&leaves);
+        let proof = new.consistency_proof(3).unwrap();
+        let relabeled =
+            ConsistencyProof::<Sha256>::new(3, 7, proof.hashes().to_vec(), HashMode::Bytes);
+
+        assert!(!proof.verify(&old.hash(), &new.hash(), HashMode::Bytes));
+        assert!(!relabeled.verify(&old.hash(), &new.hash(), new.mode()));
+    }
+}
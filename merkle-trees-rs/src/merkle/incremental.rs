@@ -0,0 +1,291 @@
+use std::io::{self, Read};
+use std::marker::PhantomData;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
+
+use super::{HashMode, MerkleLeaf, TreeShape};
+
+// Append-only tree that only keeps the right edge (one hash per level), so
+// pushing a leaf is O(log n). The root matches a MerkleTree built in one go
+// from the same leaves with the same shape, so it can stream datasets that
+// don't fit in memory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IncrementalMerkleTree<H: Hasher = Sha256> {
+    frontier: Vec<Hash>,
+    leaf_count: usize,
+    mode: HashMode,
+    shape: TreeShape,
+    hasher: PhantomData<H>,
+}
+
+impl IncrementalMerkleTree {
+    pub fn new() -> Self {
+        Self::with_mode(HashMode::default())
+    }
+
+    pub fn with_mode(mode: HashMode) -> Self {
+        Self::with_hasher(mode)
+    }
+
+    pub fn rfc6962() -> Self {
+        Self::with_shape(HashMode::DomainSeparated, TreeShape::Rfc6962)
+    }
+}
+
+impl Default for IncrementalMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher> IncrementalMerkleTree<H> {
+    pub fn with_hasher(mode: HashMode) -> Self {
+        Self::with_shape(mode, TreeShape::default())
+    }
+
+    pub fn with_shape(mode: HashMode, shape: TreeShape) -> Self {
+        Self {
+            frontier: Vec::new(),
+            leaf_count: 0,
+            mode,
+            shape,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaf_count == 0
+    }
+
+    pub fn mode(&self) -> HashMode {
+        self.mode
+    }
+
+    pub fn shape(&self) -> TreeShape {
+        self.shape
+    }
+
+    // Returns the index of the new leaf.
+    pub fn push<L: MerkleLeaf>(&mut self, leaf: L) -> usize {
+        let mut hash = self.mode.hash_leaf::<H, _>(&leaf);
+        let mut level = 0;
+
+        // Every set bit of the leaf count is a complete subtree waiting for a
+        // right sibling; merge with them until we reach an empty level.
+        while self.leaf_count & (1 << level) != 0 {
+            hash = self.mode.hash_nodes::<H>(&self.frontier[level], &hash);
+            level += 1;
+        }
+
+        if level == self.frontier.len() {
+            self.frontier.push(hash);
+        } else {
+            self.frontier[level] = hash;
+        }
+        self.leaf_count += 1;
+        self.leaf_count - 1
+    }
+
+    // Pushes every `chunk_size` bytes of the reader as a leaf, the last one
+    // possibly shorter. Returns the number of leaves pushed.
+    pub fn extend_from_reader<R: Read>(
+        &mut self,
+        mut reader: R,
+        chunk_size: usize,
+    ) -> io::Result<usize> {
+        if chunk_size == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "chunk size must not be zero",
+            ));
+        }
+
+        let mut chunk = vec![0u8; chunk_size];
+        let mut pushed = 0;
+        loop {
+            let mut filled = 0;
+            while filled < chunk_size {
+                match reader.read(&mut chunk[filled..]) {
+                    Ok(0) => break,
+                    Ok(read) => filled += read,
+                    Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(e) => return Err(e),
+                }
+            }
+            if filled > 0 {
+                self.push(&chunk[..filled]);
+                pushed += 1;
+            }
+            if filled < chunk_size {
+                return Ok(pushed);
+            }
+        }
+    }
+
+    pub fn root(&self) -> Option<Hash> {
+        if self.is_empty() {
+            return None;
+        }
+        match self.shape {
+            TreeShape::DuplicateLast => Some(self.duplicating_root()),
+            TreeShape::Rfc6962 => Some(self.promoting_root()),
+        }
+    }
+
+    fn duplicating_root(&self) -> Hash {
+        let mut count = self.leaf_count;
+        let mut level = count.trailing_zeros() as usize;
+        let mut hash = self.frontier[level];
+
+        while count != 1 << level {
+            // The lowest pending subtree has no right sibling, so it is paired
+            // with itself, as MerkleTree does with the last node of odd levels.
+            hash = self.mode.hash_nodes::<H>(&hash, &hash);
+            count += 1 << level;
+            level += 1;
+
+            while count & (1 << level) == 0 {
+                hash = self.mode.hash_nodes::<H>(&self.frontier[level], &hash);
+                level += 1;
+            }
+        }
+        hash
+    }
+
+    // The pending subtrees are merged from the smallest up; each one is the
+    // left sibling of everything pushed after it.
+    fn promoting_root(&self) -> Hash {
+        let mut pending = (0..self.frontier.len())
+            .filter(|level| self.leaf_count & (1 << level) != 0)
+            .map(|level| &self.frontier[level]);
+        let smallest = pending.next().cloned().unwrap_or_default();
+        pending.fold(smallest, |hash, left| {
+            self.mode.hash_nodes::<H>(left, &hash)
+        })
+    }
+}
+
+impl<H: Hasher, L: MerkleLeaf> Extend<L> for IncrementalMerkleTree<H> {
+    fn extend<I: IntoIterator<Item = L>>(&mut self, leaves: I) {
+        for leaf in leaves {
+            self.push(leaf);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{test_leaves as leaves, MerkleTree};
+
+    #[test]
+    fn test_empty_tree_has_no_root() {
+        let tree = IncrementalMerkleTree::new();
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.root(), None);
+    }
+
+    #[test]
+    fn test_pushes_leaves() {
+        let mut tree = IncrementalMerkleTree::new();
+
+        assert_eq!(tree.push(b"leaf 0".to_vec()), 0);
+        assert_eq!(tree.push(b"leaf 1".to_vec()), 1);
+        assert_eq!(tree.len(), 2);
+    }
+
+    #[test]
+    fn test_matches_batch_root_for_every_size() {
+        let leaves = leaves(70);
+
+        for mode in [
+            HashMode::Legacy,
+            HashMode::Bytes,
+            HashMode::Bitcoin,
+            HashMode::DomainSeparated,
+        ] {
+            let mut tree = IncrementalMerkleTree::with_mode(mode);
+            for size in 1..=leaves.len() {
+                tree.push(leaves[size - 1]);
+
+                let batch = MerkleTree::with_mode(&leaves[..size], mode);
+                assert_eq!(tree.root(), Some(batch.hash()), "size {size}, {mode:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_keeps_one_hash_per_level() {
+        let mut tree = IncrementalMerkleTree::new();
+        tree.extend(&leaves(1000));
+
+        assert_eq!(tree.frontier.len(), 10);
+    }
+
+    #[test]
+    fn test_matches_rfc6962_root_for_every_size() {
+        let leaves = leaves(70);
+        let mut tree = IncrementalMerkleTree::rfc6962();
+
+        for size in 1..=leaves.len() {
+            tree.push(leaves[size - 1]);
+
+            let batch = MerkleTree::rfc6962(&leaves[..size]);
+            assert_eq!(tree.root(), Some(batch.hash()), "size {size}");
+        }
+        assert_eq!(tree.shape(), TreeShape::Rfc6962);
+    }
+
+    // Hands out at most `step` bytes per read, like a socket.
+    struct Trickle<'a> {
+        bytes: &'a [u8],
+        step: usize,
+    }
+
+    impl Read for Trickle<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let read = self.step.min(buf.len()).min(self.bytes.len());
+            buf[..read].copy_from_slice(&self.bytes[..read]);
+            self.bytes = &self.bytes[read..];
+            Ok(read)
+        }
+    }
+
+    #[test]
+    fn test_streams_leaves_from_reader() {
+        let data: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+
+        for chunk_size in [1, 7, 64, 1000, 4096] {
+            let chunks: Vec<&[u8]> = data.chunks(chunk_size).collect();
+            for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+                let mut tree = IncrementalMerkleTree::<Sha256>::with_shape(HashMode::Bytes, shape);
+                let reader = Trickle {
+                    bytes: &data,
+                    step: 5,
+                };
+
+                assert_eq!(
+                    tree.extend_from_reader(reader, chunk_size).unwrap(),
+                    chunks.len()
+                );
+                let batch = MerkleTree::<Sha256>::with_shape(&chunks, HashMode::Bytes, shape);
+                assert_eq!(tree.root(), Some(batch.hash()), "{chunk_size}, {shape:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_reads_nothing_from_empty_reader() {
+        let mut tree = IncrementalMerkleTree::new();
+
+        assert_eq!(tree.extend_from_reader(&b""[..], 64).unwrap(), 0);
+        assert_eq!(tree.root(), None);
+        assert!(tree.extend_from_reader(&b"data"[..], 0).is_err());
+    }
+}
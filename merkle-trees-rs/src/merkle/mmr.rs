@@ -0,0 +1,330 @@
+use std::marker::PhantomData;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
+
+use super::MerkleLeaf;
+
+// Append-only list of perfect trees ("mountains"), one per set bit of the
+// leaf count. levels[0] holds the leaves and levels[k][i] the parent of
+// levels[k - 1][2i] and levels[k - 1][2i + 1]; the last node of a level with
+// an odd length is a peak.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MerkleMountainRange<H: Hasher = Sha256> {
+    levels: Vec<Vec<Hash>>,
+    hasher: PhantomData<H>,
+}
+
+impl MerkleMountainRange {
+    pub fn new() -> Self {
+        Self::with_hasher()
+    }
+}
+
+impl Default for MerkleMountainRange {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher> MerkleMountainRange<H> {
+    pub fn with_hasher() -> Self {
+        Self {
+            levels: vec![Vec::new()],
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.levels[0].len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.levels[0].is_empty()
+    }
+
+    // Returns the index of the new leaf.
+    pub fn push<L: MerkleLeaf>(&mut self, leaf: L) -> usize {
+        self.levels[0].push(leaf.leaf_hash::<H>());
+
+        let mut level = 0;
+        while self.levels[level].len().is_multiple_of(2) {
+            let nodes = &self.levels[level];
+            let parent = hash_nodes::<H>(&nodes[nodes.len() - 2], &nodes[nodes.len() - 1]);
+            if level + 1 == self.levels.len() {
+                self.levels.push(Vec::new());
+            }
+            self.levels[level + 1].push(parent);
+            level += 1;
+        }
+
+        self.len() - 1
+    }
+
+    // Mountain roots from the highest (leftmost) to the lowest.
+    pub fn peaks(&self) -> Vec<Hash> {
+        self.levels
+            .iter()
+            .rev()
+            .filter(|nodes| nodes.len() % 2 == 1)
+            .map(|nodes| nodes[nodes.len() - 1])
+            .collect()
+    }
+
+    pub fn bag_peaks(&self) -> Option<Hash> {
+        bag_peaks::<H>(&self.peaks())
+    }
+
+    // Commits to the bagged peaks and the number of leaves.
+    pub fn root(&self) -> Option<Hash> {
+        root::<H>(self.len(), &self.peaks())
+    }
+
+    pub fn proof(&self, index: usize) -> Option<MmrProof<H>> {
+        if index >= self.len() {
+            return None;
+        }
+
+        let mut path = Vec::new();
+        let mut position = index;
+        let mut height = 0;
+        while let Some(sibling) = self.levels[height].get(position ^ 1) {
+            path.push(*sibling);
+            position /= 2;
+            height += 1;
+        }
+
+        let mut peaks = self.peaks();
+        peaks.remove(higher_peaks(self.len(), height));
+
+        Some(MmrProof::new(index, self.len(), path, peaks))
+    }
+}
+
+// Proof that a leaf is in a MerkleMountainRange of a given size: the path up
+// to the peak of its mountain, then every other peak.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MmrProof<H: Hasher = Sha256> {
+    index: usize,
+    leaf_count: usize,
+    path: Vec<Hash>,
+    peaks: Vec<Hash>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> MmrProof<H> {
+    pub fn new(index: usize, leaf_count: usize, path: Vec<Hash>, peaks: Vec<Hash>) -> Self {
+        Self {
+            index,
+            leaf_count,
+            path,
+            peaks,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn path(&self) -> &[Hash] {
+        &self.path
+    }
+
+    pub fn peaks(&self) -> &[Hash] {
+        &self.peaks
+    }
+
+    pub fn root<L: MerkleLeaf + ?Sized>(&self, leaf: &L) -> Option<Hash> {
+        if self.index >= self.leaf_count {
+            return None;
+        }
+
+        let mut hash = leaf.leaf_hash::<H>();
+        let mut position = self.index;
+        let mut height = 0;
+        let mut path = self.path.iter();
+        // A node has a sibling while the pair fits in its level.
+        while (position ^ 1) < self.leaf_count >> height {
+            let sibling = path.next()?;
+            hash = if position.is_multiple_of(2) {
+                hash_nodes::<H>(&hash, sibling)
+            } else {
+                hash_nodes::<H>(sibling, &hash)
+            };
+            position /= 2;
+            height += 1;
+        }
+        if path.next().is_some() {
+            return None;
+        }
+
+        if self.peaks.len() + 1 != self.leaf_count.count_ones() as usize {
+            return None;
+        }
+        let mut peaks = self.peaks.clone();
+        peaks.insert(higher_peaks(self.leaf_count, height), hash);
+
+        root::<H>(self.leaf_count, &peaks)
+    }
+
+    pub fn verify<L: MerkleLeaf + ?Sized>(&self, root: &Hash, leaf: &L) -> bool {
+        self.root(leaf).as_ref() == Some(root)
+    }
+}
+
+fn hash_nodes<H: Hasher>(left: &Hash, right: &Hash) -> Hash {
+    H::hash(&[left.to_bytes(), right.to_bytes()].concat())
+}
+
+// Peaks are ordered by height, so the one at `height` comes after every
+// higher one.
+fn higher_peaks(leaf_count: usize, height: usize) -> usize {
+    (leaf_count >> (height + 1)).count_ones() as usize
+}
+
+// Folds the peaks from right to left.
+fn bag_peaks<H: Hasher>(peaks: &[Hash]) -> Option<Hash> {
+    let (last, rest) = peaks.split_last()?;
+    Some(
+        rest.iter()
+            .rev()
+            .fold(*last, |bag, peak| hash_nodes::<H>(peak, &bag)),
+    )
+}
+
+fn root<H: Hasher>(leaf_count: usize, peaks: &[Hash]) -> Option<Hash> {
+    let bag = bag_peaks::<H>(peaks)?;
+    Some(H::hash(
+        &[&(leaf_count as u64).to_le_bytes()[..], &bag.to_bytes()].concat(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::test_leaves as leaves;
+
+    #[test]
+    fn test_empty_range_has_no_root() {
+        let mmr = MerkleMountainRange::new();
+
+        assert!(mmr.is_empty());
+        assert!(mmr.peaks().is_empty());
+        assert_eq!(mmr.root(), None);
+        assert!(mmr.proof(0).is_none());
+    }
+
+    #[test]
+    fn test_has_one_peak_per_set_bit() {
+        let mut mmr = MerkleMountainRange::new();
+        for (i, leaf) in leaves(70).into_iter().enumerate() {
+            assert_eq!(mmr.push(leaf), i);
+            assert_eq!(mmr.peaks().len(), (i + 1).count_ones() as usize);
+        }
+    }
+
+    #[test]
+    fn test_peaks_are_perfect_tree_roots() {
+        let leaves = leaves(7);
+        let mut mmr = MerkleMountainRange::new();
+        leaves.iter().for_each(|leaf| {
+            mmr.push(leaf);
+        });
+
+        let h = hash_nodes::<Sha256>;
+        let first = h(&h(&leaves[0], &leaves[1]), &h(&leaves[2], &leaves[3]));
+        let second = h(&leaves[4], &leaves[5]);
+        assert_eq!(mmr.peaks(), vec![first, second, leaves[6]]);
+        assert_eq!(mmr.bag_peaks(), Some(h(&first, &h(&second, &leaves[6]))));
+    }
+
+    #[test]
+    fn test_root_commits_to_size() {
+        let mut mmr = MerkleMountainRange::new();
+        mmr.push(Hash::from_bytes(b"block"));
+        let one = mmr.root();
+        mmr.push(Hash::from_bytes(b"block"));
+
+        assert_ne!(mmr.root(), one);
+    }
+
+    #[test]
+    fn test_verifies_every_proof() {
+        for count in 1..40 {
+            let leaves = leaves(count);
+            let mut mmr = MerkleMountainRange::new();
+            leaves.iter().for_each(|leaf| {
+                mmr.push(leaf);
+            });
+            let root = mmr.root().unwrap();
+
+            for (index, leaf) in leaves.iter().enumerate() {
+                let proof = mmr.proof(index).unwrap();
+                assert!(proof.verify(&root, leaf), "{index} of {count}");
+                assert!(!proof.verify(&root, &Hash::from_bytes(b"other")));
+            }
+            assert!(mmr.proof(count).is_none());
+        }
+    }
+
+    #[test]
+    fn test_old_proofs_do_not_verify_against_new_root() {
+        let mut mmr = MerkleMountainRange::new();
+        let leaves = leaves(10);
+        leaves[..5].iter().for_each(|leaf| {
+            mmr.push(leaf);
+        });
+        let proof = mmr.proof(2).unwrap();
+        leaves[5..].iter().for_each(|leaf| {
+            mmr.push(leaf);
+        });
+
+        assert!(!proof.verify(&mmr.root().unwrap(), &leaves[2]));
+        assert!(mmr
+            .proof(2)
+            .unwrap()
+            .verify(&mmr.root().unwrap(), &leaves[2]));
+    }
+
+    #[test]
+    fn test_rejects_tampered_position_and_peaks() {
+        let leaves = leaves(11);
+        let mut mmr = MerkleMountainRange::new();
+        leaves.iter().for_each(|leaf| {
+            mmr.push(leaf);
+        });
+        let root = mmr.root().unwrap();
+        let proof = mmr.proof(4).unwrap();
+
+        let moved = MmrProof::<Sha256>::new(
+            5,
+            proof.leaf_count(),
+            proof.path().to_vec(),
+            proof.peaks().to_vec(),
+        );
+        assert!(!moved.verify(&root, &leaves[4]));
+
+        let resized = MmrProof::<Sha256>::new(
+            4,
+            proof.leaf_count() + 1,
+            proof.path().to_vec(),
+            proof.peaks().to_vec(),
+        );
+        assert!(!resized.verify(&root, &leaves[4]));
+
+        let dropped = MmrProof::<Sha256>::new(
+            4,
+            proof.leaf_count(),
+            proof.path().to_vec(),
+            proof.peaks()[1..].to_vec(),
+        );
+        assert!(!dropped.verify(&root, &leaves[4]));
+    }
+}
@@ -0,0 +1,1619 @@
+mod consistency;
+mod incremental;
+mod mmr;
+mod multiproof;
+mod render;
+mod sparse;
+mod sync;
+
+pub use consistency::ConsistencyProof;
+pub use incremental::IncrementalMerkleTree;
+pub use mmr::{MerkleMountainRange, MmrProof};
+pub use multiproof::MultiProof;
+pub use sparse::{SparseMerkleProof, SparseMerkleTree};
+pub use sync::{SyncError, SyncRequest, SyncResponse, SyncSession, DEFAULT_MAX_LEAVES};
+
+use std::borrow::Cow;
+use std::marker::PhantomData;
+use std::ops::Range;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
+use crate::transaction::Transaction;
+
+// A node of a MerkleTree, by level (0 for the leaves) and index within it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct NodeId {
+    level: usize,
+    index: usize,
+}
+
+impl NodeId {
+    // The root position of every tree with up to `leaf_count` leaves.
+    fn top(leaf_count: usize) -> Self {
+        NodeId {
+            level: leaf_count.max(1).next_power_of_two().trailing_zeros() as usize,
+            index: 0,
+        }
+    }
+
+    fn children(self) -> [NodeId; 2] {
+        let left = NodeId {
+            level: self.level - 1,
+            index: self.index * 2,
+        };
+        [
+            left,
+            NodeId {
+                index: left.index + 1,
+                ..left
+            },
+        ]
+    }
+}
+
+// Sorted leaf indices merged into runs, followed by `tail`, the leaves only
+// the larger of two trees has.
+fn to_ranges(indices: &[usize], tail: Range<usize>) -> Vec<Range<usize>> {
+    let mut ranges: Vec<Range<usize>> = Vec::new();
+    for &index in indices {
+        match ranges.last_mut() {
+            Some(range) if range.end == index => range.end += 1,
+            _ => ranges.push(index..index + 1),
+        }
+    }
+    match ranges.last_mut() {
+        _ if tail.is_empty() => {}
+        Some(range) if range.end == tail.start => range.end = tail.end,
+        _ => ranges.push(tail),
+    }
+    ranges
+}
+
+// The leaves one tree has and the other doesn't.
+fn tail(leaf_count: usize, other: usize) -> Range<usize> {
+    leaf_count.min(other)..leaf_count.max(other)
+}
+
+// Anything that can be committed to by a MerkleTree. Leaves are hashed from
+// their bytes with the tree's hasher, hashes are used as they are.
+pub trait MerkleLeaf {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]>;
+
+    fn leaf_hash<H: Hasher>(&self) -> Hash {
+        H::hash(&self.leaf_bytes())
+    }
+}
+
+impl MerkleLeaf for Hash {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.to_bytes().to_vec())
+    }
+
+    fn leaf_hash<H: Hasher>(&self) -> Hash {
+        *self
+    }
+}
+
+impl MerkleLeaf for Transaction {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Owned(self.to_bytes())
+    }
+}
+
+impl MerkleLeaf for [u8] {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl MerkleLeaf for Vec<u8> {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        Cow::Borrowed(self)
+    }
+}
+
+impl<T: MerkleLeaf + ?Sized> MerkleLeaf for &T {
+    fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+        (**self).leaf_bytes()
+    }
+
+    fn leaf_hash<H: Hasher>(&self) -> Hash {
+        (**self).leaf_hash::<H>()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum HashMode {
+    // Hashes the concatenated hex strings of both children. Kept so roots built
+    // before the byte mode existed can still be checked.
+    Legacy,
+    #[default]
+    Bytes,
+    // Bitcoin consensus rule: the hasher is applied twice over both children
+    // in internal byte order, i.e. double SHA-256 with the default hasher.
+    Bitcoin,
+    // RFC 6962 style: leaf bytes and interior nodes are hashed with different
+    // prefixes, so an interior node can't be passed off as a leaf.
+    DomainSeparated,
+}
+
+// Smallest level worth splitting across threads. Without the parallel
+// feature every level is hashed on the calling thread.
+#[cfg(feature = "parallel")]
+pub const PARALLEL_THRESHOLD: usize = 1 << 12;
+#[cfg(not(feature = "parallel"))]
+const PARALLEL_THRESHOLD: usize = usize::MAX;
+
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+impl HashMode {
+    pub fn hash_leaf<H: Hasher, L: MerkleLeaf + ?Sized>(&self, leaf: &L) -> Hash {
+        match self {
+            HashMode::DomainSeparated => {
+                H::hash(&[&[LEAF_PREFIX][..], &leaf.leaf_bytes()].concat())
+            }
+            _ => leaf.leaf_hash::<H>(),
+        }
+    }
+
+    pub fn hash_nodes<H: Hasher>(&self, left: &Hash, right: &Hash) -> Hash {
+        match self {
+            HashMode::Legacy => H::hash(format!("{}{}", left, right).as_bytes()),
+            HashMode::Bytes => H::hash(&Self::concat(left, right)),
+            HashMode::Bitcoin => H::hash(&H::hash(&Self::concat(left, right)).to_bytes()),
+            HashMode::DomainSeparated => {
+                H::hash(&[&[NODE_PREFIX][..], &Self::concat(left, right)].concat())
+            }
+        }
+    }
+
+    fn concat(left: &Hash, right: &Hash) -> [u8; 64] {
+        let mut bytes = [0u8; 64];
+        bytes[0..32].copy_from_slice(&left.to_bytes());
+        bytes[32..64].copy_from_slice(&right.to_bytes());
+        bytes
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TreeShape {
+    // The last node of an odd level is paired with itself, as Bitcoin does.
+    #[default]
+    DuplicateLast,
+    // The last node of an odd level moves up unchanged, so the left subtree
+    // always holds the largest power of two leaves (RFC 6962 / 9162).
+    Rfc6962,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Position {
+    Left,
+    Right,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ProofStep {
+    pub hash: Hash,
+    pub position: Position,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MerkleProof<H: Hasher = Sha256> {
+    index: usize,
+    steps: Vec<ProofStep>,
+    mode: HashMode,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> MerkleProof<H> {
+    pub fn new(index: usize, steps: Vec<ProofStep>, mode: HashMode) -> Self {
+        Self {
+            index,
+            steps,
+            mode,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    pub fn mode(&self) -> HashMode {
+        self.mode
+    }
+
+    pub fn steps(&self) -> &[ProofStep] {
+        &self.steps
+    }
+
+    pub fn root<L: MerkleLeaf + ?Sized>(&self, leaf: &L) -> Hash {
+        let leaf = self.mode.hash_leaf::<H, _>(leaf);
+        self.steps
+            .iter()
+            .fold(leaf, |hash, step| match step.position {
+                Position::Left => self.mode.hash_nodes::<H>(&step.hash, &hash),
+                Position::Right => self.mode.hash_nodes::<H>(&hash, &step.hash),
+            })
+    }
+
+    // Only the root is needed to check a proof, so clients don't have to hold the tree.
+    // The mode comes from the verifier: a proof carrying another mode could pass
+    // off an interior node as a leaf.
+    pub fn verify<L: MerkleLeaf + ?Sized>(&self, root: &Hash, leaf: &L, mode: HashMode) -> bool {
+        self.mode == mode && self.root(leaf) == *root
+    }
+
+    // Brings this proof up to date after another leaf changed, given that
+    // leaf's new value and its proof from the updated tree. Both paths go
+    // through the same nodes down to where they split, so the steps above
+    // that node are taken from the new proof and the step at it is rehashed
+    // from the changed leaf. Returns false when the paths never split, so the
+    // proofs can't come from the same tree.
+    pub fn refresh<L: MerkleLeaf + ?Sized>(&mut self, changed: &MerkleProof<H>, leaf: &L) -> bool {
+        if changed.mode != self.mode {
+            return false;
+        }
+        if changed.index == self.index {
+            self.steps = changed.steps.clone();
+            return true;
+        }
+
+        let shared = self
+            .steps
+            .iter()
+            .rev()
+            .zip(changed.steps.iter().rev())
+            .take_while(|(ours, theirs)| ours.position == theirs.position)
+            .count();
+        if shared == self.steps.len() || shared == changed.steps.len() {
+            return false;
+        }
+        let ours = self.steps.len() - shared - 1;
+        let theirs = changed.steps.len() - shared - 1;
+
+        let below =
+            MerkleProof::<H>::new(changed.index, changed.steps[..theirs].to_vec(), self.mode);
+        self.steps[ours].hash = below.root(leaf);
+        self.steps[ours + 1..].clone_from_slice(&changed.steps[theirs + 1..]);
+        true
+    }
+}
+
+#[derive(Debug, Clone, Eq)]
+pub struct MerkleTree<H: Hasher = Sha256> {
+    // Every level of the tree, leaves first, in one buffer. Level k starts at
+    // levels[k] and the root is the last hash.
+    nodes: Vec<Hash>,
+    levels: Vec<usize>,
+    leaf_count: usize,
+    mode: HashMode,
+    shape: TreeShape,
+    // Sibling pairs with equal hashes, kept up to date by update_leaf.
+    equal_siblings: usize,
+    hasher: PhantomData<H>,
+}
+
+impl MerkleTree {
+    pub fn new<L: MerkleLeaf>(leaves: impl IntoIterator<Item = L>) -> Self {
+        Self::with_mode(leaves, HashMode::default())
+    }
+
+    pub fn with_mode<L: MerkleLeaf>(leaves: impl IntoIterator<Item = L>, mode: HashMode) -> Self {
+        Self::with_hasher(leaves, mode)
+    }
+
+    // Certificate Transparency compatible tree: SHA-256 with 0x00/0x01
+    // prefixes and no duplicated nodes.
+    pub fn rfc6962<L: MerkleLeaf>(leaves: impl IntoIterator<Item = L>) -> Self {
+        Self::with_shape(leaves, HashMode::DomainSeparated, TreeShape::Rfc6962)
+    }
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    pub fn with_hasher<L: MerkleLeaf>(leaves: impl IntoIterator<Item = L>, mode: HashMode) -> Self {
+        Self::with_shape(leaves, mode, TreeShape::default())
+    }
+
+    pub fn with_shape<L: MerkleLeaf>(
+        leaves: impl IntoIterator<Item = L>,
+        mode: HashMode,
+        shape: TreeShape,
+    ) -> Self {
+        Self::build(leaves, mode, shape, PARALLEL_THRESHOLD)
+    }
+
+    // Levels with fewer nodes than `threshold` are hashed on the calling
+    // thread. The root is the same whatever the threshold.
+    #[cfg(feature = "parallel")]
+    pub fn with_parallel_threshold<L: MerkleLeaf>(
+        leaves: impl IntoIterator<Item = L>,
+        mode: HashMode,
+        shape: TreeShape,
+        threshold: usize,
+    ) -> Self {
+        Self::build(leaves, mode, shape, threshold)
+    }
+
+    fn build<L: MerkleLeaf>(
+        leaves: impl IntoIterator<Item = L>,
+        mode: HashMode,
+        shape: TreeShape,
+        parallel_threshold: usize,
+    ) -> Self {
+        let leaves: Vec<Hash> = leaves
+            .into_iter()
+            .map(|leaf| mode.hash_leaf::<H, _>(&leaf))
+            .collect();
+        let leaf_count = leaves.len();
+        let (nodes, levels, equal_siblings) =
+            Self::build_tree(leaves, mode, shape, parallel_threshold);
+        Self {
+            nodes,
+            levels,
+            leaf_count,
+            mode,
+            shape,
+            equal_siblings,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn hash(&self) -> Hash {
+        self.nodes[self.nodes.len() - 1]
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn mode(&self) -> HashMode {
+        self.mode
+    }
+
+    pub fn shape(&self) -> TreeShape {
+        self.shape
+    }
+
+    // True when two sibling nodes had the same hash while building the tree,
+    // e.g. [a, b, c, c], which has the same root as [a, b, c] (CVE-2012-2459).
+    pub fn is_mutated(&self) -> bool {
+        // Without duplicated nodes, equal siblings don't make two leaf lists
+        // share a root.
+        self.equal_siblings > 0 && self.shape == TreeShape::DuplicateLast
+    }
+
+    pub fn proof(&self, index: usize) -> Option<MerkleProof<H>> {
+        if index >= self.leaf_count {
+            return None;
+        }
+        Some(MerkleProof::new(index, self.merkle_path(index), self.mode))
+    }
+
+    pub fn proof_for<L: MerkleLeaf + ?Sized>(&self, leaf: &L) -> Option<MerkleProof<H>> {
+        let leaf = self.mode.hash_leaf::<H, _>(leaf);
+        let index = self.level(0).iter().position(|hash| *hash == leaf)?;
+        self.proof(index)
+    }
+
+    // Replaces one leaf and rehashes only its path to the root. Returns the
+    // hash of the leaf it replaced.
+    pub fn update_leaf<L: MerkleLeaf + ?Sized>(&mut self, index: usize, leaf: &L) -> Option<Hash> {
+        if index >= self.leaf_count {
+            return None;
+        }
+
+        let previous = self.level(0)[index];
+        let mut hash = self.mode.hash_leaf::<H, _>(leaf);
+        let mut index = index;
+        for level in 0..self.levels.len() {
+            let start = self.levels[level];
+            let len = self.level(level).len();
+            let old = std::mem::replace(&mut self.nodes[start + index], hash);
+
+            let sibling = index ^ 1;
+            if sibling < len {
+                let sibling = &self.nodes[start + sibling];
+                self.equal_siblings -= (old == *sibling) as usize;
+                self.equal_siblings += (hash == *sibling) as usize;
+            }
+            if level + 1 == self.levels.len() {
+                break;
+            }
+
+            hash = if sibling >= len {
+                match self.shape {
+                    TreeShape::DuplicateLast => self.mode.hash_nodes::<H>(&hash, &hash),
+                    TreeShape::Rfc6962 => hash,
+                }
+            } else if index.is_multiple_of(2) {
+                self.mode
+                    .hash_nodes::<H>(&hash, &self.nodes[start + sibling])
+            } else {
+                self.mode
+                    .hash_nodes::<H>(&self.nodes[start + sibling], &hash)
+            };
+            index /= 2;
+        }
+        Some(previous)
+    }
+
+    // Proves that the first `old_size` leaves of this tree form the tree the
+    // old root was computed from. Only defined for the RFC 6962 shape.
+    pub fn consistency_proof(&self, old_size: usize) -> Option<ConsistencyProof<H>> {
+        if self.shape != TreeShape::Rfc6962 || old_size == 0 || old_size > self.leaf_count {
+            return None;
+        }
+        let hashes = self.consistency_path(self.root_id(), old_size, true)?;
+        Some(ConsistencyProof::new(
+            old_size,
+            self.leaf_count,
+            hashes,
+            self.mode,
+        ))
+    }
+
+    // A single proof for several leaves, sharing the sibling hashes their
+    // paths have in common.
+    pub fn multiproof(&self, indices: &[usize]) -> Option<MultiProof<H>> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        if indices.is_empty() || indices[indices.len() - 1] >= self.leaf_count {
+            return None;
+        }
+
+        let mut hashes = Vec::new();
+        self.multi_path(self.root_id(), 0, self.leaf_count, &indices, &mut hashes);
+        Some(MultiProof::new(
+            self.leaf_count,
+            indices,
+            hashes,
+            self.mode,
+            self.shape,
+        ))
+    }
+
+    // Indices of the leaves that differ from `other`, including leaves only
+    // one of the trees has.
+    pub fn diff(&self, other: &MerkleTree<H>) -> Vec<usize> {
+        self.diff_ranges(other).into_iter().flatten().collect()
+    }
+
+    // Same as diff, with runs of adjacent leaves merged into ranges. Walks
+    // both trees down from the root and skips every subtree whose hash they
+    // share. Leaves only one tree has are reported as one range without
+    // being visited.
+    pub fn diff_ranges(&self, other: &MerkleTree<H>) -> Vec<Range<usize>> {
+        let top = NodeId::top(self.leaf_count.max(other.leaf_count));
+        let mut indices = Vec::new();
+        self.diff_path(other, top, &mut indices);
+        to_ranges(&indices, tail(self.leaf_count, other.leaf_count))
+    }
+
+    // Each level is appended to the buffer as it is hashed from the one below.
+    fn build_tree(
+        leaves: Vec<Hash>,
+        mode: HashMode,
+        shape: TreeShape,
+        parallel_threshold: usize,
+    ) -> (Vec<Hash>, Vec<usize>, usize) {
+        let mut nodes = leaves;
+        // Each level above the leaves is at most half the one below, rounded up.
+        nodes.reserve(nodes.len() + usize::BITS as usize);
+        let mut levels = vec![0];
+
+        let mut equal_siblings = 0;
+        while nodes.len() - levels[levels.len() - 1] > 1 {
+            let (start, end) = (levels[levels.len() - 1], nodes.len());
+            let level = &nodes[start..end];
+            equal_siblings += level
+                .chunks_exact(2)
+                .filter(|pair| pair[0] == pair[1])
+                .count();
+            let next = Self::hash_level(level, mode, shape, parallel_threshold);
+            levels.push(end);
+            nodes.extend(next);
+        }
+        (nodes, levels, equal_siblings)
+    }
+
+    #[cfg_attr(not(feature = "parallel"), allow(unused_variables))]
+    fn hash_level(
+        level: &[Hash],
+        mode: HashMode,
+        shape: TreeShape,
+        parallel_threshold: usize,
+    ) -> Vec<Hash> {
+        let parent = |pair: &[Hash]| match pair {
+            [left, right] => mode.hash_nodes::<H>(left, right),
+            _ if shape == TreeShape::Rfc6962 => pair[0],
+            _ => mode.hash_nodes::<H>(&pair[0], &pair[0]),
+        };
+
+        #[cfg(feature = "parallel")]
+        if level.len() >= parallel_threshold {
+            use rayon::prelude::*;
+            return level.par_chunks(2).map(parent).collect();
+        }
+        level.chunks(2).map(parent).collect()
+    }
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    fn level(&self, level: usize) -> &[Hash] {
+        let end = self
+            .levels
+            .get(level + 1)
+            .copied()
+            .unwrap_or(self.nodes.len());
+        &self.nodes[self.levels[level]..end]
+    }
+
+    fn root_id(&self) -> NodeId {
+        NodeId {
+            level: self.levels.len() - 1,
+            index: 0,
+        }
+    }
+
+    fn node(&self, id: NodeId) -> &Hash {
+        &self.level(id.level)[id.index]
+    }
+
+    fn get_node(&self, id: NodeId) -> Option<&Hash> {
+        if id.level >= self.levels.len() {
+            return None;
+        }
+        self.level(id.level).get(id.index)
+    }
+
+    fn diff_path(&self, other: &MerkleTree<H>, id: NodeId, indices: &mut Vec<usize>) {
+        if !self.differs_at(id, other.get_node(id), other.leaf_count) {
+            return;
+        }
+        if id.level == 0 {
+            indices.push(id.index);
+            return;
+        }
+        for child in id.children() {
+            self.diff_path(other, child, indices);
+        }
+    }
+
+    // Whether the leaves under `id` that both trees have may differ from
+    // those of a tree with `leaf_count` leaves and `theirs` at the same
+    // position. Node `id` covers the same leaf positions in both trees, but
+    // with different sizes a shared hash only proves those leaves equal when
+    // neither tree padded the range.
+    fn differs_at(&self, id: NodeId, theirs: Option<&Hash>, leaf_count: usize) -> bool {
+        let start = id.index << id.level;
+        if start >= self.leaf_count.min(leaf_count) {
+            return false;
+        }
+        let end = (id.index + 1) << id.level;
+        let ours = self.get_node(id);
+        let padded = self.leaf_count != leaf_count && end > self.leaf_count.min(leaf_count);
+        ours.is_none() || ours != theirs || padded
+    }
+
+    // Leaves below a node, counting duplicated ones.
+    fn size(&self, id: NodeId) -> usize {
+        match self.shape {
+            TreeShape::DuplicateLast => 1 << id.level,
+            TreeShape::Rfc6962 => (1 << id.level).min(self.leaf_count - (id.index << id.level)),
+        }
+    }
+
+    // A last node without a sibling is paired with itself, or with the RFC
+    // 6962 shape moved up unchanged, in which case its children are those of
+    // the node it was moved from.
+    fn children(&self, id: NodeId) -> Option<(NodeId, NodeId)> {
+        let mut id = id;
+        while id.level > 0 {
+            let left = NodeId {
+                level: id.level - 1,
+                index: id.index * 2,
+            };
+            let right = NodeId {
+                index: left.index + 1,
+                ..left
+            };
+            if right.index < self.level(right.level).len() {
+                return Some((left, right));
+            }
+            if self.shape == TreeShape::DuplicateLast {
+                return Some((left, left));
+            }
+            id = left;
+        }
+        None
+    }
+
+    fn merkle_path(&self, index: usize) -> Vec<ProofStep> {
+        let mut steps = Vec::new();
+        let mut index = index;
+        for level in 0..self.levels.len() - 1 {
+            let nodes = self.level(level);
+            let sibling = if index % 2 == 1 {
+                Some((index - 1, Position::Left))
+            } else if index + 1 < nodes.len() {
+                Some((index + 1, Position::Right))
+            } else if self.shape == TreeShape::DuplicateLast {
+                Some((index, Position::Right))
+            } else {
+                None
+            };
+            if let Some((sibling, position)) = sibling {
+                steps.push(ProofStep {
+                    hash: nodes[sibling],
+                    position,
+                });
+            }
+            index /= 2;
+        }
+        steps
+    }
+
+    // RFC 9162 SUBPROOF, walking the same left/right sizes as merkle_path.
+    fn consistency_path(&self, id: NodeId, old_size: usize, complete: bool) -> Option<Vec<Hash>> {
+        if old_size == self.size(id) {
+            return Some(if complete {
+                Vec::new()
+            } else {
+                vec![*self.node(id)]
+            });
+        }
+
+        let (left, right) = self.children(id)?;
+        if old_size <= self.size(left) {
+            let mut path = self.consistency_path(left, old_size, complete)?;
+            path.push(*self.node(right));
+            Some(path)
+        } else {
+            let mut path = self.consistency_path(right, old_size - self.size(left), false)?;
+            path.push(*self.node(left));
+            Some(path)
+        }
+    }
+
+    // Hashes of the subtrees without any of the sorted `indices`, depth first.
+    // `real` counts the leaves that aren't duplicates: a right subtree without
+    // any is a copy of its left sibling, so the verifier rebuilds it itself.
+    fn multi_path(
+        &self,
+        id: NodeId,
+        offset: usize,
+        real: usize,
+        indices: &[usize],
+        path: &mut Vec<Hash>,
+    ) {
+        if indices.is_empty() {
+            path.push(*self.node(id));
+            return;
+        }
+
+        if let Some((left, right)) = self.children(id) {
+            let left_size = self.size(left);
+            let split = indices.partition_point(|&index| index < offset + left_size);
+            self.multi_path(left, offset, real.min(left_size), &indices[..split], path);
+            if real > left_size {
+                self.multi_path(
+                    right,
+                    offset + left_size,
+                    real - left_size,
+                    &indices[split..],
+                    path,
+                );
+            }
+        }
+    }
+}
+
+impl<H: Hasher> PartialEq for MerkleTree<H> {
+    fn eq(&self, other: &Self) -> bool {
+        self.hash() == other.hash()
+    }
+}
+
+// Distinct leaves for the tests of every tree in this module.
+#[cfg(test)]
+fn test_leaves(count: usize) -> Vec<Hash> {
+    (0..count)
+        .map(|i| Hash::from_bytes(&i.to_le_bytes()))
+        .collect()
+}
+
+// Test module
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{mainnet_block_100000_tx_ids, rpc_hash};
+    use crate::hasher::{Blake3, DoubleSha256, Keccak256};
+    use crate::timestamp::Timestamp;
+
+    fn create_test_transaction(id: &str) -> Transaction {
+        Transaction::new(
+            1,
+            "alice".to_string(),
+            format!("recipient_{}", id),
+            1000,
+            Some(Timestamp::new(0)),
+        )
+    }
+
+    #[test]
+    fn test_creates_leaf_with_data() {
+        let tx = create_test_transaction("Tx1");
+        let tree = MerkleTree::new([&tx]);
+        assert_eq!(tree.level(0), &[tx.tx_id()]);
+        assert_eq!(tree.hash(), tx.tx_id());
+    }
+
+    #[test]
+    fn test_creates_node_with_left_and_right() {
+        let tx1 = create_test_transaction("Tx1");
+        let tx2 = create_test_transaction("Tx2");
+        let tree = MerkleTree::with_mode([&tx1, &tx2], HashMode::Legacy);
+
+        let combined_hash = Hash::digest_str(&format!("{}{}", tx1.tx_id(), tx2.tx_id()));
+        assert_eq!(tree.hash(), combined_hash);
+        assert_eq!(
+            tree.children(tree.root_id()).map(|(_, right)| right.index),
+            Some(1)
+        );
+    }
+
+    #[test]
+    fn test_creates_node_hashing_raw_bytes() {
+        let tx1 = create_test_transaction("Tx1");
+        let tx2 = create_test_transaction("Tx2");
+        let tree = MerkleTree::with_mode([&tx1, &tx2], HashMode::Bytes);
+
+        let combined_bytes = [tx1.tx_id().to_bytes(), tx2.tx_id().to_bytes()].concat();
+        assert_eq!(tree.hash(), Hash::from_bytes(&combined_bytes));
+    }
+
+    #[test]
+    fn test_creates_merkle_tree() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+            create_test_transaction("Tx4"),
+        ];
+        let tree = MerkleTree::with_mode(leaves, HashMode::Legacy);
+        assert_eq!(
+            tree.hash(),
+            "39af34a258981e8ce8fc8ae00e672204318b024f1d8f53c955ec4537082a6873".to_string()
+        );
+        assert_eq!(tree.size(tree.root_id()), 4);
+    }
+
+    #[test]
+    fn test_creates_merkle_tree_with_odd_number_of_leaves() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+        ];
+        let tree = MerkleTree::with_mode(leaves, HashMode::Legacy);
+        assert_eq!(
+            tree.hash(),
+            "4af4e4be22e129326516eb57290f3063721e3c88b35493206dd04dd4847bb30a".to_string()
+        );
+        assert_eq!(tree.size(tree.root_id()), 4);
+    }
+
+    #[test]
+    fn test_stores_levels_in_one_buffer() {
+        let leaves: Vec<Hash> = (0..5u8).map(|i| Hash::from_bytes(&[i])).collect();
+
+        for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+            let tree = MerkleTree::<Sha256>::with_shape(&leaves, HashMode::Bytes, shape);
+
+            assert_eq!(tree.levels, vec![0, 5, 8, 10]);
+            assert_eq!(tree.nodes.len(), 11);
+            assert_eq!(tree.level(0), &leaves[..]);
+            assert_eq!(tree.node(tree.root_id()), &tree.hash());
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_build_matches_sequential() {
+        let leaves: Vec<Hash> = (0..1000u32)
+            .map(|i| Hash::from_bytes(&i.to_le_bytes()))
+            .collect();
+
+        for size in [1, 2, 3, 5, 64, 999, 1000] {
+            for mode in [
+                HashMode::Legacy,
+                HashMode::Bitcoin,
+                HashMode::DomainSeparated,
+            ] {
+                for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+                    let leaves = &leaves[..size];
+                    let sequential = MerkleTree::<Sha256>::with_parallel_threshold(
+                        leaves,
+                        mode,
+                        shape,
+                        usize::MAX,
+                    );
+                    let parallel =
+                        MerkleTree::<Sha256>::with_parallel_threshold(leaves, mode, shape, 1);
+
+                    assert_eq!(parallel.nodes, sequential.nodes);
+                    assert_eq!(parallel.levels, sequential.levels);
+                    assert_eq!(
+                        parallel.hash(),
+                        MerkleTree::<Sha256>::with_shape(leaves, mode, shape).hash()
+                    );
+                }
+            }
+        }
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn test_parallel_build_detects_mutation() {
+        let tx_ids = mainnet_block_100000_tx_ids();
+        let mut mutated = tx_ids.clone();
+        mutated.push(tx_ids[3]);
+
+        let tree = MerkleTree::<Sha256>::with_parallel_threshold(
+            &mutated[1..],
+            HashMode::Bitcoin,
+            TreeShape::DuplicateLast,
+            1,
+        );
+
+        assert!(tree.is_mutated());
+    }
+
+    #[test]
+    fn test_updates_leaf_like_rebuilding() {
+        let leaves: Vec<Hash> = (0..20u8).map(|i| Hash::from_bytes(&[i])).collect();
+        let new_leaf = Hash::from_bytes(b"new");
+
+        for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+            for size in 1..=leaves.len() {
+                for index in 0..size {
+                    let mut tree =
+                        MerkleTree::<Sha256>::with_shape(&leaves[..size], HashMode::Bytes, shape);
+                    let mut expected = leaves[..size].to_vec();
+                    expected[index] = new_leaf;
+
+                    assert_eq!(tree.update_leaf(index, &new_leaf), Some(leaves[index]));
+                    let rebuilt =
+                        MerkleTree::<Sha256>::with_shape(&expected, HashMode::Bytes, shape);
+                    assert_eq!(tree.nodes, rebuilt.nodes, "leaf {index} of {size}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_does_not_update_leaf_out_of_range() {
+        let mut tree = MerkleTree::new(vec![create_test_transaction("Tx1")]);
+        let root = tree.hash();
+
+        assert_eq!(tree.update_leaf(1, &create_test_transaction("Tx2")), None);
+        assert_eq!(tree.hash(), root);
+    }
+
+    #[test]
+    fn test_updating_leaf_tracks_mutation() {
+        let leaves: Vec<Hash> = (0..4u8).map(|i| Hash::from_bytes(&[i])).collect();
+        let mut tree = MerkleTree::with_mode(&leaves, HashMode::Bitcoin);
+        assert!(!tree.is_mutated());
+
+        tree.update_leaf(3, &leaves[2]);
+        assert!(tree.is_mutated());
+
+        tree.update_leaf(3, &leaves[3]);
+        assert!(!tree.is_mutated());
+    }
+
+    #[test]
+    fn test_refreshes_proofs_after_other_leaf_changes() {
+        let leaves: Vec<Hash> = (0..13u8).map(|i| Hash::from_bytes(&[i])).collect();
+        let new_leaf = Hash::from_bytes(b"new");
+
+        for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+            for size in 1..=leaves.len() {
+                for changed in 0..size {
+                    let mut tree =
+                        MerkleTree::<Sha256>::with_shape(&leaves[..size], HashMode::Bytes, shape);
+                    let mut proofs: Vec<_> = (0..size).map(|i| tree.proof(i).unwrap()).collect();
+
+                    tree.update_leaf(changed, &new_leaf);
+                    let changed_proof = tree.proof(changed).unwrap();
+
+                    for (index, proof) in proofs.iter_mut().enumerate() {
+                        assert!(
+                            proof.refresh(&changed_proof, &new_leaf),
+                            "{shape:?} {index}, {changed} of {size}"
+                        );
+                        assert_eq!(
+                            proof,
+                            &tree.proof(index).unwrap(),
+                            "{index}, {changed} of {size}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_does_not_refresh_proof_with_other_mode() {
+        let leaves: Vec<Hash> = (0..8u8).map(|i| Hash::from_bytes(&[i])).collect();
+        let tree = MerkleTree::new(&leaves);
+        let bitcoin = MerkleTree::with_mode(&leaves, HashMode::Bitcoin);
+
+        let mut proof = tree.proof(1).unwrap();
+        assert!(!proof.refresh(&bitcoin.proof(5).unwrap(), &leaves[5]));
+        assert_eq!(proof, tree.proof(1).unwrap());
+    }
+
+    #[test]
+    fn test_creates_proof_for_first_index() {
+        let tx1 = create_test_transaction("Tx1");
+        let tx2 = create_test_transaction("Tx2");
+        let tx3 = create_test_transaction("Tx3");
+        let tx4 = create_test_transaction("Tx4");
+
+        let leaves = vec![tx1.clone(), tx2.clone(), tx3.clone(), tx4.clone()];
+        let tree = MerkleTree::new(leaves);
+        let proof_unwrapped = tree.merkle_path(0);
+
+        let expected = vec![
+            ProofStep {
+                hash: tx2.tx_id(),
+                position: Position::Right,
+            },
+            ProofStep {
+                hash: HashMode::Bytes.hash_nodes::<Sha256>(&tx3.tx_id(), &tx4.tx_id()),
+                position: Position::Right,
+            },
+        ];
+
+        assert_eq!(proof_unwrapped, expected);
+
+        let proof = MerkleProof::<Sha256>::new(0, proof_unwrapped, HashMode::Bytes);
+        assert!(proof.verify(&tree.hash(), &tx1.tx_id(), tree.mode()));
+        assert!(!proof.verify(
+            &tree.hash(),
+            &create_test_transaction("Tx5").tx_id(),
+            tree.mode()
+        ));
+    }
+
+    #[test]
+    fn test_creates_proof_for_second_index() {
+        let tx1 = create_test_transaction("Tx1");
+        let tx2 = create_test_transaction("Tx2");
+        let tx3 = create_test_transaction("Tx3");
+        let tx4 = create_test_transaction("Tx4");
+
+        let leaves = vec![tx1, tx2.clone(), tx3, tx4.clone()];
+        let tree = MerkleTree::new(leaves);
+        let proof = tree.merkle_path(1);
+
+        let tx1_ref = create_test_transaction("Tx1");
+        let expected = vec![
+            ProofStep {
+                hash: tx1_ref.tx_id(),
+                position: Position::Left,
+            },
+            ProofStep {
+                hash: HashMode::Bytes
+                    .hash_nodes::<Sha256>(&create_test_transaction("Tx3").tx_id(), &tx4.tx_id()),
+                position: Position::Right,
+            },
+        ];
+
+        assert_eq!(proof, expected);
+        assert!(
+            MerkleProof::<Sha256>::new(1, proof, HashMode::Bytes).verify(
+                &tree.hash(),
+                &tx2.tx_id(),
+                tree.mode()
+            )
+        );
+    }
+
+    #[test]
+    fn test_verifies_leaf_with_valid_proof() {
+        let tx1 = create_test_transaction("Tx1");
+        let tx2 = create_test_transaction("Tx2");
+        let tx3 = create_test_transaction("Tx3");
+        let tx4 = create_test_transaction("Tx4");
+
+        let leaves = vec![tx1.clone(), tx2.clone(), tx3, tx4.clone()];
+        // let leaves = vec![
+        //     "Tx1".to_string(),
+        //     "Tx2".to_string(),
+        //     "Tx3".to_string(),
+        //     "Tx4".to_string(),
+        // ];
+        let tree = MerkleTree::new(leaves);
+        let proof0 = tree.proof(0);
+        let proof1 = tree.proof(1);
+
+        // assert!(proof0.unwrap().verify(&tree.hash(), &Hash::digest_str("Tx1")));
+        assert!(proof0
+            .unwrap()
+            .verify(&tree.hash(), &tx1.tx_id(), tree.mode()));
+        assert!(!proof1
+            .unwrap()
+            .verify(&tree.hash(), &tx1.tx_id(), tree.mode()));
+    }
+
+    #[test]
+    fn test_creates_proof_for_tx_id() {
+        let leaves: Vec<Transaction> = ["Tx1", "Tx2", "Tx3"]
+            .iter()
+            .map(|id| create_test_transaction(id))
+            .collect();
+        let tree = MerkleTree::new(leaves.clone());
+
+        for (index, tx) in leaves.iter().enumerate() {
+            let proof = tree.proof_for(&tx.tx_id()).unwrap();
+            assert_eq!(proof.index(), index);
+            assert!(proof.verify(&tree.hash(), &tx.tx_id(), tree.mode()));
+        }
+
+        assert_eq!(
+            tree.proof_for(&create_test_transaction("Tx4").tx_id()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_does_not_create_proof_out_of_range() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+        ];
+        let tree = MerkleTree::new(leaves);
+
+        assert_eq!(tree.leaf_count(), 3);
+        assert!(tree.proof(2).is_some());
+        assert_eq!(tree.proof(3), None);
+    }
+
+    #[test]
+    fn test_does_not_verify_proof_against_other_root() {
+        let tx1 = create_test_transaction("Tx1");
+        let tree = MerkleTree::new(vec![tx1.clone(), create_test_transaction("Tx2")]);
+        let other = MerkleTree::new(vec![tx1.clone(), create_test_transaction("Tx3")]);
+
+        let proof = tree.proof(0).unwrap();
+        assert!(proof.verify(&tree.hash(), &tx1.tx_id(), tree.mode()));
+        assert!(!proof.verify(&other.hash(), &tx1.tx_id(), tree.mode()));
+    }
+
+    #[test]
+    fn test_creates_byte_mode_tree_by_default() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+            create_test_transaction("Tx4"),
+        ];
+        let tree = MerkleTree::new(leaves.clone());
+        let legacy = MerkleTree::with_mode(leaves, HashMode::Legacy);
+
+        assert_eq!(tree.mode(), HashMode::Bytes);
+        assert_ne!(tree.hash(), legacy.hash());
+    }
+
+    #[test]
+    fn test_verifies_proofs_in_both_modes() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+        ];
+
+        for mode in [HashMode::Legacy, HashMode::Bytes] {
+            let tree = MerkleTree::with_mode(leaves.clone(), mode);
+            for (index, tx) in leaves.iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert_eq!(proof.mode(), mode);
+                assert!(proof.verify(&tree.hash(), &tx.tx_id(), tree.mode()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_does_not_verify_proof_with_other_mode() {
+        let tx1 = create_test_transaction("Tx1");
+        let leaves = vec![tx1.clone(), create_test_transaction("Tx2")];
+        let tree = MerkleTree::with_mode(leaves.clone(), HashMode::Legacy);
+        let proof = MerkleTree::new(leaves).proof(0).unwrap();
+
+        assert!(!proof.verify(&tree.hash(), &tx1.tx_id(), tree.mode()));
+    }
+
+    #[test]
+    fn test_reproduces_bitcoin_merkle_root() {
+        let tree = MerkleTree::with_mode(mainnet_block_100000_tx_ids(), HashMode::Bitcoin);
+
+        assert_eq!(
+            tree.hash(),
+            rpc_hash("f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766")
+        );
+    }
+
+    #[test]
+    fn test_single_transaction_bitcoin_root_is_tx_id() {
+        let coinbase = rpc_hash("4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b");
+        let tree = MerkleTree::with_mode(vec![coinbase], HashMode::Bitcoin);
+
+        assert_eq!(tree.hash(), coinbase);
+    }
+
+    #[test]
+    fn test_bitcoin_mode_duplicates_last_node() {
+        let mut tx_ids = mainnet_block_100000_tx_ids();
+        tx_ids.truncate(3);
+        let tree = MerkleTree::with_mode(tx_ids.clone(), HashMode::Bitcoin);
+
+        let left = Hash::double_from_bytes(&[tx_ids[0].to_bytes(), tx_ids[1].to_bytes()].concat());
+        let right = Hash::double_from_bytes(&[tx_ids[2].to_bytes(), tx_ids[2].to_bytes()].concat());
+        let root = Hash::double_from_bytes(&[left.to_bytes(), right.to_bytes()].concat());
+
+        assert_eq!(tree.hash(), root);
+    }
+
+    #[test]
+    fn test_verifies_bitcoin_proof() {
+        let tx_ids = mainnet_block_100000_tx_ids();
+        let tree = MerkleTree::with_mode(tx_ids.clone(), HashMode::Bitcoin);
+        let proof = tree.proof_for(&tx_ids[2]).unwrap();
+
+        assert_eq!(proof.index(), 2);
+        assert!(proof.verify(
+            &rpc_hash("f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766"),
+            &tx_ids[2],
+            tree.mode()
+        ));
+    }
+
+    fn forged_leaf_proof(tree: &MerkleTree) -> (Hash, MerkleProof) {
+        // Present the left interior node as if it were a leaf, with the right
+        // subtree as its only sibling.
+        let (left, right) = tree.children(tree.root_id()).unwrap();
+        let proof = MerkleProof::new(
+            0,
+            vec![ProofStep {
+                hash: *tree.node(right),
+                position: Position::Right,
+            }],
+            tree.mode(),
+        );
+        (*tree.node(left), proof)
+    }
+
+    #[test]
+    fn test_forged_leaf_verifies_without_domain_separation() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+            create_test_transaction("Tx4"),
+        ];
+
+        for mode in [HashMode::Legacy, HashMode::Bytes] {
+            let tree = MerkleTree::with_mode(leaves.clone(), mode);
+            let (forged_leaf, proof) = forged_leaf_proof(&tree);
+
+            assert!(proof.verify(&tree.hash(), &forged_leaf, tree.mode()));
+        }
+    }
+
+    #[test]
+    fn test_rejects_forged_leaf_with_domain_separation() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+            create_test_transaction("Tx4"),
+        ];
+        let tree = MerkleTree::with_mode(leaves, HashMode::DomainSeparated);
+        let (forged_leaf, proof) = forged_leaf_proof(&tree);
+
+        assert!(!proof.verify(&tree.hash(), &forged_leaf, tree.mode()));
+        for mode in [HashMode::Legacy, HashMode::Bytes, HashMode::Bitcoin] {
+            let proof = MerkleProof::<Sha256>::new(0, proof.steps().to_vec(), mode);
+            assert!(!proof.verify(&tree.hash(), &forged_leaf, tree.mode()));
+        }
+    }
+
+    #[test]
+    fn test_rejects_forged_byte_leaf_with_other_mode() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+        ];
+        let tree = MerkleTree::with_mode(leaves, HashMode::DomainSeparated);
+        let (left, right) = tree.children(tree.root_id()).unwrap();
+        // Hashed as plain bytes, the root's preimage is a leaf on its own.
+        let forged_leaf = [
+            &[NODE_PREFIX][..],
+            &tree.node(left).to_bytes(),
+            &tree.node(right).to_bytes(),
+        ]
+        .concat();
+        let proof = MerkleProof::<Sha256>::new(0, vec![], HashMode::Bytes);
+
+        assert_eq!(proof.root(&forged_leaf), tree.hash());
+        assert!(!proof.verify(&tree.hash(), &forged_leaf, tree.mode()));
+    }
+
+    #[test]
+    fn test_prefixes_leaves_and_nodes() {
+        let tx1 = create_test_transaction("Tx1");
+        let tx2 = create_test_transaction("Tx2");
+        let tree = MerkleTree::with_mode(vec![tx1.clone(), tx2.clone()], HashMode::DomainSeparated);
+
+        let leaf1 = Hash::from_bytes(&[&[0x00][..], &tx1.to_bytes()[..]].concat());
+        let leaf2 = Hash::from_bytes(&[&[0x00][..], &tx2.to_bytes()[..]].concat());
+        let root = Hash::from_bytes(&[&[0x01][..], &leaf1.to_bytes(), &leaf2.to_bytes()].concat());
+
+        assert_eq!(tree.hash(), root);
+    }
+
+    #[test]
+    fn test_verifies_domain_separated_proofs() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+        ];
+        let tree = MerkleTree::with_mode(leaves.clone(), HashMode::DomainSeparated);
+
+        for (index, tx) in leaves.iter().enumerate() {
+            let proof = tree.proof_for(tx).unwrap();
+            assert_eq!(proof.index(), index);
+            assert!(proof.verify(&tree.hash(), tx, tree.mode()));
+            assert!(!proof.verify(&tree.hash(), &tx.tx_id(), tree.mode()));
+        }
+    }
+
+    #[test]
+    fn test_detects_duplicated_last_leaf() {
+        let tx1 = create_test_transaction("Tx1");
+        let tx2 = create_test_transaction("Tx2");
+        let tx3 = create_test_transaction("Tx3");
+
+        let tree = MerkleTree::new(vec![tx1.clone(), tx2.clone(), tx3.clone()]);
+        let mutated = MerkleTree::new(vec![tx1, tx2, tx3.clone(), tx3]);
+
+        assert_eq!(tree.hash(), mutated.hash());
+        assert!(!tree.is_mutated());
+        assert!(mutated.is_mutated());
+    }
+
+    #[test]
+    fn test_detects_duplicated_subtree() {
+        let leaves: Vec<Transaction> = ["Tx1", "Tx2", "Tx3", "Tx4", "Tx5", "Tx6"]
+            .iter()
+            .map(|id| create_test_transaction(id))
+            .collect();
+        let mut mutated_leaves = leaves.clone();
+        mutated_leaves.extend_from_slice(&leaves[4..6]);
+
+        let tree = MerkleTree::with_mode(leaves, HashMode::Bitcoin);
+        let mutated = MerkleTree::with_mode(mutated_leaves, HashMode::Bitcoin);
+
+        assert_eq!(tree.hash(), mutated.hash());
+        assert!(!tree.is_mutated());
+        assert!(mutated.is_mutated());
+    }
+
+    #[test]
+    fn test_uses_tx_id_as_transaction_leaf() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+        ];
+        let tx_ids: Vec<Hash> = leaves.iter().map(Transaction::tx_id).collect();
+
+        assert_eq!(
+            MerkleTree::new(leaves).hash(),
+            MerkleTree::new(tx_ids).hash()
+        );
+    }
+
+    #[test]
+    fn test_creates_merkle_tree_from_bytes() {
+        let chunks: Vec<Vec<u8>> = vec![
+            b"chunk 1".to_vec(),
+            b"chunk 2".to_vec(),
+            b"chunk 3".to_vec(),
+        ];
+        let tree = MerkleTree::new(chunks.iter());
+
+        let hashes: Vec<Hash> = chunks.iter().map(|chunk| Hash::from_bytes(chunk)).collect();
+        assert_eq!(tree.hash(), MerkleTree::new(hashes).hash());
+
+        let proof = tree.proof_for(b"chunk 2".as_slice()).unwrap();
+        assert_eq!(proof.index(), 1);
+        assert!(proof.verify(&tree.hash(), b"chunk 2".as_slice(), tree.mode()));
+        assert!(!proof.verify(&tree.hash(), b"chunk 4".as_slice(), tree.mode()));
+    }
+
+    #[test]
+    fn test_creates_merkle_tree_from_custom_leaves() {
+        struct Account {
+            name: &'static str,
+            balance: u64,
+        }
+
+        impl MerkleLeaf for Account {
+            fn leaf_bytes(&self) -> Cow<'_, [u8]> {
+                Cow::Owned([self.name.as_bytes(), &self.balance.to_le_bytes()].concat())
+            }
+        }
+
+        let accounts = vec![
+            Account {
+                name: "alice",
+                balance: 10,
+            },
+            Account {
+                name: "bob",
+                balance: 20,
+            },
+        ];
+        let tree = MerkleTree::new(&accounts);
+        let proof = tree.proof_for(&accounts[1]).unwrap();
+
+        assert!(proof.verify(&tree.hash(), &accounts[1], tree.mode()));
+        assert!(!proof.verify(&tree.hash(), &accounts[0], tree.mode()));
+    }
+
+    #[test]
+    fn test_reproduces_bitcoin_merkle_root_with_double_sha256_hasher() {
+        let tree =
+            MerkleTree::<DoubleSha256>::with_hasher(mainnet_block_100000_tx_ids(), HashMode::Bytes);
+
+        assert_eq!(
+            tree.hash(),
+            rpc_hash("f3e94742aca4b5ef85488dc37c06c3282295ffec960994b2c0d5ac2a25a95766")
+        );
+    }
+
+    #[test]
+    fn test_hashes_leaves_and_nodes_with_tree_hasher() {
+        let chunks: Vec<&[u8]> = vec![b"chunk 1", b"chunk 2"];
+        let tree = MerkleTree::<Keccak256>::with_hasher(&chunks, HashMode::Bytes);
+
+        let left = Keccak256::hash(b"chunk 1");
+        let right = Keccak256::hash(b"chunk 2");
+        let root = Keccak256::hash(&[left.to_bytes(), right.to_bytes()].concat());
+
+        assert_eq!(tree.hash(), root);
+        assert_ne!(tree.hash(), MerkleTree::new(&chunks).hash());
+    }
+
+    #[test]
+    fn test_verifies_proofs_with_other_hashers() {
+        let leaves = vec![
+            create_test_transaction("Tx1"),
+            create_test_transaction("Tx2"),
+            create_test_transaction("Tx3"),
+        ];
+        let tree = MerkleTree::<Blake3>::with_hasher(&leaves, HashMode::DomainSeparated);
+
+        for (index, tx) in leaves.iter().enumerate() {
+            let proof = tree.proof_for(tx).unwrap();
+            assert_eq!(proof.index(), index);
+            assert!(proof.verify(&tree.hash(), tx, tree.mode()));
+            assert!(!proof.verify(&tree.hash(), &tx.tx_id(), tree.mode()));
+        }
+    }
+
+    // Leaves and roots from the Certificate Transparency reference tests.
+    fn ct_leaves() -> Vec<Vec<u8>> {
+        [
+            "",
+            "00",
+            "10",
+            "2021",
+            "3031",
+            "40414243",
+            "5051525354555657",
+            "606162636465666768696a6b6c6d6e6f",
+        ]
+        .iter()
+        .map(|hex| {
+            (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect()
+        })
+        .collect()
+    }
+
+    #[test]
+    fn test_reproduces_rfc6962_roots() {
+        let roots = [
+            "6e340b9cffb37a989ca544e6bb780a2c78901d3fb33738768511a30617afa01d",
+            "fac54203e7cc696cf0dfcb42c92a1d9dbaf70ad9e621f4bd8d98662f00e3c125",
+            "aeb6bcfe274b70a14fb067a5e5578264db0fa9b51af5e0ba159158f329e06e77",
+            "d37ee418976dd95753c1c73862b9398fa2a2cf9b4ff0fdfe8b30cd95209614b7",
+            "4e3bbb1f7b478dcfe71fb631631519a3bca12c9aefca1612bfce4c13a86264d4",
+            "76e67dadbcdf1e10e1b74ddc608abd2f98dfb16fbce75277b5232a127f2087ef",
+            "ddb89be403809e325750d3d263cd78929c2942b7942a34b77e122c9594a74c8c",
+            "5dc9da79a70659a9ad559cb701ded9a2ab9d823aad2f4960cfe370eff4604328",
+        ];
+        let leaves = ct_leaves();
+
+        for (size, root) in roots.iter().enumerate() {
+            let tree = MerkleTree::rfc6962(&leaves[..size + 1]);
+            assert_eq!(tree.hash(), *root);
+            assert!(!tree.is_mutated());
+        }
+    }
+
+    #[test]
+    fn test_promotes_odd_node_in_rfc6962_shape() {
+        let leaves = ct_leaves();
+        let tree =
+            MerkleTree::<Sha256>::with_shape(&leaves[..3], HashMode::Bytes, TreeShape::Rfc6962);
+
+        let left = HashMode::Bytes
+            .hash_nodes::<Sha256>(&Hash::from_bytes(&leaves[0]), &Hash::from_bytes(&leaves[1]));
+        let root = HashMode::Bytes.hash_nodes::<Sha256>(&left, &Hash::from_bytes(&leaves[2]));
+
+        assert_eq!(tree.shape(), TreeShape::Rfc6962);
+        assert_eq!(tree.hash(), root);
+    }
+
+    #[test]
+    fn test_verifies_rfc6962_inclusion_proofs() {
+        let leaves = ct_leaves();
+
+        for size in 1..=leaves.len() {
+            let tree = MerkleTree::rfc6962(&leaves[..size]);
+            for (index, leaf) in leaves[..size].iter().enumerate() {
+                let proof = tree.proof(index).unwrap();
+                assert!(
+                    proof.verify(&tree.hash(), leaf, tree.mode()),
+                    "leaf {index} of {size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_does_not_create_consistency_proof_for_duplicating_tree() {
+        let tree = MerkleTree::new(ct_leaves());
+
+        assert_eq!(tree.consistency_proof(3), None);
+    }
+
+    #[test]
+    fn test_diffs_trees_like_comparing_every_leaf() {
+        let leaves: Vec<Hash> = (0..13u8).map(|i| Hash::from_bytes(&[i])).collect();
+        for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+            for (old_size, new_size) in [(13, 13), (8, 13), (13, 5), (1, 4), (7, 8)] {
+                let mut changed = leaves[..new_size].to_vec();
+                changed[new_size / 2] = Hash::from_bytes(b"changed");
+                let old =
+                    MerkleTree::<Sha256>::with_shape(&leaves[..old_size], HashMode::Bytes, shape);
+                let new = MerkleTree::<Sha256>::with_shape(&changed, HashMode::Bytes, shape);
+
+                let expected: Vec<usize> = (0..old_size.max(new_size))
+                    .filter(|&i| leaves[..old_size].get(i) != changed.get(i))
+                    .collect();
+                assert_eq!(old.diff(&new), expected, "{old_size} -> {new_size}");
+                assert_eq!(new.diff(&old), expected, "{new_size} -> {old_size}");
+            }
+        }
+    }
+
+    #[test]
+    fn test_diff_of_equal_trees_is_empty() {
+        let tree = MerkleTree::new(ct_leaves());
+
+        assert!(tree.diff(&tree.clone()).is_empty());
+    }
+
+    #[test]
+    fn test_merges_adjacent_differences_into_ranges() {
+        let old = MerkleTree::new(ct_leaves());
+        let mut leaves = ct_leaves();
+        leaves[1] = b"one".to_vec();
+        leaves[2] = b"two".to_vec();
+        leaves[5] = b"five".to_vec();
+        leaves.push(b"eight".to_vec());
+
+        assert_eq!(
+            old.diff_ranges(&MerkleTree::new(leaves)),
+            vec![1..3, 5..6, 8..9]
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serializes_proofs() {
+        let leaves: Vec<Hash> = (0..5u8).map(|i| Hash::from_bytes(&[i])).collect();
+        let tree = MerkleTree::<Keccak256>::with_hasher(&leaves, HashMode::DomainSeparated);
+        let proof = tree.proof(3).unwrap();
+
+        let json = serde_json::to_value(&proof).unwrap();
+        assert_eq!(json["index"], 3);
+        assert_eq!(json["mode"], "DomainSeparated");
+        assert_eq!(json["steps"][0]["hash"], proof.steps()[0].hash.to_hex());
+        assert_eq!(json["steps"][0]["position"], "Left");
+
+        let decoded: MerkleProof<Keccak256> = serde_json::from_value(json).unwrap();
+        assert_eq!(decoded, proof);
+        let decoded: MerkleProof<Keccak256> =
+            bincode::deserialize(&bincode::serialize(&proof).unwrap()).unwrap();
+        assert!(decoded.verify(&tree.hash(), &leaves[3], tree.mode()));
+    }
+
+    #[test]
+    fn test_verifies_every_proof_with_other_hasher() {
+        let leaves = test_leaves(11);
+        let shape = TreeShape::Rfc6962;
+        let tree = MerkleTree::<Blake3>::with_shape(&leaves, HashMode::DomainSeparated, shape);
+        let old = MerkleTree::<Blake3>::with_shape(&leaves[..6], HashMode::DomainSeparated, shape);
+        assert_ne!(tree.hash(), MerkleTree::rfc6962(&leaves).hash());
+
+        assert!(tree
+            .proof(3)
+            .unwrap()
+            .verify(&tree.hash(), &leaves[3], tree.mode()));
+        assert!(tree.multiproof(&[0, 10]).unwrap().verify(
+            &tree.hash(),
+            &[leaves[0], leaves[10]],
+            tree.mode(),
+            tree.shape()
+        ));
+        assert!(tree
+            .consistency_proof(6)
+            .unwrap()
+            .verify(&old.hash(), &tree.hash(), tree.mode()));
+
+        let mut incremental =
+            IncrementalMerkleTree::<Blake3>::with_shape(HashMode::DomainSeparated, shape);
+        incremental.extend(&leaves);
+        assert_eq!(incremental.root(), Some(tree.hash()));
+
+        let mut mmr = MerkleMountainRange::<Blake3>::with_hasher();
+        leaves.iter().for_each(|leaf| {
+            mmr.push(leaf);
+        });
+        assert!(mmr
+            .proof(3)
+            .unwrap()
+            .verify(&mmr.root().unwrap(), &leaves[3]));
+
+        let mut sparse = SparseMerkleTree::<Blake3>::with_hasher();
+        sparse.insert(&leaves[0], &leaves[1]);
+        assert_ne!(sparse.root(), SparseMerkleTree::new().root());
+        assert!(sparse
+            .proof(&leaves[0])
+            .verify_inclusion(&sparse.root(), &leaves[0], &leaves[1]));
+        assert!(sparse
+            .proof(&leaves[2])
+            .verify_exclusion(&sparse.root(), &leaves[2]));
+    }
+}
@@ -0,0 +1,340 @@
+use std::marker::PhantomData;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
+
+use super::{HashMode, MerkleLeaf, TreeShape};
+
+// Inclusion proof for several leaves at once. It only carries the hashes of
+// the subtrees that contain none of the proven leaves; everything else is
+// rebuilt from the leaves by the verifier.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiProof<H: Hasher = Sha256> {
+    leaf_count: usize,
+    indices: Vec<usize>,
+    hashes: Vec<Hash>,
+    mode: HashMode,
+    shape: TreeShape,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> MultiProof<H> {
+    pub fn new(
+        leaf_count: usize,
+        indices: Vec<usize>,
+        hashes: Vec<Hash>,
+        mode: HashMode,
+        shape: TreeShape,
+    ) -> Self {
+        Self {
+            leaf_count,
+            indices,
+            hashes,
+            mode,
+            shape,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn leaf_count(&self) -> usize {
+        self.leaf_count
+    }
+
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
+    pub fn hashes(&self) -> &[Hash] {
+        &self.hashes
+    }
+
+    pub fn mode(&self) -> HashMode {
+        self.mode
+    }
+
+    pub fn shape(&self) -> TreeShape {
+        self.shape
+    }
+
+    // `leaves` must be given in the same order as `indices`.
+    pub fn root<L: MerkleLeaf>(&self, leaves: &[L]) -> Option<Hash> {
+        if leaves.len() != self.indices.len() || self.leaf_count == 0 {
+            return None;
+        }
+        let sorted = self.indices.windows(2).all(|pair| pair[0] < pair[1]);
+        if !sorted || self.indices.iter().any(|&index| index >= self.leaf_count) {
+            return None;
+        }
+
+        let leaves: Vec<(usize, Hash)> = self
+            .indices
+            .iter()
+            .zip(leaves)
+            .map(|(&index, leaf)| (index, self.mode.hash_leaf::<H, _>(leaf)))
+            .collect();
+        // A forged leaf count may have no power of two above it.
+        let size = match self.shape {
+            TreeShape::DuplicateLast => self.leaf_count.checked_next_power_of_two()?,
+            TreeShape::Rfc6962 => self.leaf_count,
+        };
+
+        let mut hashes = self.hashes.iter();
+        let root = self.rebuild(0, size, self.leaf_count, &leaves, &mut hashes)?;
+        hashes.next().is_none().then_some(root)
+    }
+
+    // The mode and shape come from the verifier, not from the proof.
+    pub fn verify<L: MerkleLeaf>(
+        &self,
+        root: &Hash,
+        leaves: &[L],
+        mode: HashMode,
+        shape: TreeShape,
+    ) -> bool {
+        self.mode == mode && self.shape == shape && self.root(leaves).as_ref() == Some(root)
+    }
+
+    // Mirrors Node::multi_path, deriving the subtree sizes from the leaf count.
+    fn rebuild<'a>(
+        &self,
+        offset: usize,
+        size: usize,
+        real: usize,
+        leaves: &[(usize, Hash)],
+        hashes: &mut impl Iterator<Item = &'a Hash>,
+    ) -> Option<Hash> {
+        if leaves.is_empty() {
+            return hashes.next().cloned();
+        }
+        if size == 1 {
+            return Some(leaves[0].1);
+        }
+
+        let half = match self.shape {
+            TreeShape::DuplicateLast => size / 2,
+            TreeShape::Rfc6962 => size.checked_next_power_of_two()? / 2,
+        };
+        let split = leaves.partition_point(|(index, _)| *index < offset + half);
+
+        let left = self.rebuild(offset, half, real.min(half), &leaves[..split], hashes)?;
+        let right = if real > half {
+            self.rebuild(
+                offset + half,
+                size - half,
+                real - half,
+                &leaves[split..],
+                hashes,
+            )?
+        } else {
+            left
+        };
+        Some(self.mode.hash_nodes::<H>(&left, &right))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{test_leaves as leaves, MerkleTree};
+
+    fn pick(leaves: &[Hash], indices: &[usize]) -> Vec<Hash> {
+        indices.iter().map(|&index| leaves[index]).collect()
+    }
+
+    #[test]
+    fn test_verifies_multiproofs() {
+        let leaves = leaves(23);
+        let index_sets: [&[usize]; 6] = [
+            &[0],
+            &[22],
+            &[0, 1],
+            &[3, 4, 5, 17],
+            &[1, 7, 8, 20, 21, 22],
+            &[0, 2, 4, 6, 8, 10, 12, 14, 16, 18, 20, 22],
+        ];
+
+        for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+            for mode in [HashMode::Bytes, HashMode::DomainSeparated] {
+                for size in 1..=leaves.len() {
+                    let tree = MerkleTree::<Sha256>::with_shape(&leaves[..size], mode, shape);
+                    for indices in index_sets {
+                        let indices: Vec<usize> =
+                            indices.iter().copied().filter(|&i| i < size).collect();
+                        if indices.is_empty() {
+                            continue;
+                        }
+
+                        let proof = tree.multiproof(&indices).unwrap();
+                        assert!(
+                            proof.verify(
+                                &tree.hash(),
+                                &pick(&leaves, &indices),
+                                tree.mode(),
+                                tree.shape()
+                            ),
+                            "{indices:?} of {size}, {mode:?}, {shape:?}"
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_sorts_and_deduplicates_indices() {
+        let leaves = leaves(8);
+        let tree = MerkleTree::new(&leaves);
+        let proof = tree.multiproof(&[5, 1, 5]).unwrap();
+
+        assert_eq!(proof.indices(), &[1, 5]);
+        assert!(proof.verify(
+            &tree.hash(),
+            &pick(&leaves, &[1, 5]),
+            tree.mode(),
+            tree.shape()
+        ));
+    }
+
+    #[test]
+    fn test_only_carries_hashes_it_needs() {
+        let leaves = leaves(8);
+        let tree = MerkleTree::new(&leaves);
+
+        // Leaves 0..4 make up the whole left subtree, so only the right root is needed.
+        assert_eq!(tree.multiproof(&[0, 1, 2, 3]).unwrap().hashes().len(), 1);
+        assert_eq!(
+            tree.multiproof(&[0, 1, 2, 3, 4, 5, 6, 7])
+                .unwrap()
+                .hashes()
+                .len(),
+            0
+        );
+        assert_eq!(tree.multiproof(&[0, 7]).unwrap().hashes().len(), 4);
+    }
+
+    #[test]
+    fn test_is_smaller_than_single_proofs() {
+        let leaves = leaves(1024);
+        let tree = MerkleTree::new(&leaves);
+        let indices: Vec<usize> = (128..160).collect();
+
+        let multiproof = tree.multiproof(&indices).unwrap();
+        let single: usize = indices
+            .iter()
+            .map(|&index| tree.proof(index).unwrap().steps().len())
+            .sum();
+
+        assert_eq!(single, 32 * 10);
+        assert_eq!(multiproof.hashes().len(), 5);
+    }
+
+    #[test]
+    fn test_rejects_wrong_leaves() {
+        let leaves = leaves(10);
+        let tree = MerkleTree::new(&leaves);
+        let proof = tree.multiproof(&[2, 6]).unwrap();
+
+        assert!(!proof.verify(
+            &tree.hash(),
+            &pick(&leaves, &[2, 7]),
+            tree.mode(),
+            tree.shape()
+        ));
+        assert!(!proof.verify(
+            &tree.hash(),
+            &pick(&leaves, &[6, 2]),
+            tree.mode(),
+            tree.shape()
+        ));
+        assert!(!proof.verify(
+            &tree.hash(),
+            &pick(&leaves, &[2]),
+            tree.mode(),
+            tree.shape()
+        ));
+    }
+
+    #[test]
+    fn test_rejects_proof_with_other_mode_or_shape() {
+        let leaves = leaves(8);
+        let tree = MerkleTree::new(&leaves);
+        let proven = pick(&leaves, &[1, 5]);
+        let proof = tree.multiproof(&[1, 5]).unwrap();
+        // With a power of two leaves both shapes give the same root.
+        let reshaped = MultiProof::<Sha256>::new(
+            8,
+            vec![1, 5],
+            proof.hashes().to_vec(),
+            proof.mode(),
+            TreeShape::Rfc6962,
+        );
+        assert_eq!(reshaped.root(&proven), Some(tree.hash()));
+
+        assert!(!reshaped.verify(&tree.hash(), &proven, tree.mode(), tree.shape()));
+        assert!(!proof.verify(&tree.hash(), &proven, HashMode::Bitcoin, tree.shape()));
+    }
+
+    #[test]
+    fn test_rejects_tampered_indices() {
+        let leaves = leaves(10);
+        let tree = MerkleTree::new(&leaves);
+        let proof = tree.multiproof(&[2, 6]).unwrap();
+        let with_indices = |indices: Vec<usize>| {
+            MultiProof::<Sha256>::new(
+                10,
+                indices,
+                proof.hashes().to_vec(),
+                proof.mode(),
+                proof.shape(),
+            )
+        };
+
+        let unsorted = with_indices(vec![6, 2]);
+        assert!(!unsorted.verify(
+            &tree.hash(),
+            &pick(&leaves, &[6, 2]),
+            tree.mode(),
+            tree.shape()
+        ));
+        let out_of_range = with_indices(vec![2, 16]);
+        assert!(!out_of_range.verify(
+            &tree.hash(),
+            &pick(&leaves, &[2, 6]),
+            tree.mode(),
+            tree.shape()
+        ));
+    }
+
+    #[test]
+    fn test_rejects_huge_leaf_count() {
+        let leaves = leaves(10);
+        let tree = MerkleTree::new(&leaves);
+        let proof = tree.multiproof(&[2, 6]).unwrap();
+
+        for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+            let forged = MultiProof::<Sha256>::new(
+                usize::MAX,
+                vec![2, 6],
+                proof.hashes().to_vec(),
+                proof.mode(),
+                shape,
+            );
+            assert!(!forged.verify(
+                &tree.hash(),
+                &pick(&leaves, &[2, 6]),
+                tree.mode(),
+                tree.shape()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_does_not_create_multiproof_out_of_range() {
+        let tree = MerkleTree::new(leaves(4));
+
+        assert_eq!(tree.multiproof(&[]), None);
+        assert_eq!(tree.multiproof(&[1, 4]), None);
+    }
+}
@@ -0,0 +1,269 @@
+use std::fmt::Write;
+
+use crate::hash::Hash;
+use crate::hasher::Hasher;
+
+use super::{MerkleProof, MerkleTree, NodeId};
+
+// Hex digits of each hash shown in a rendering.
+const HASH_DIGITS: usize = 8;
+
+// How a node relates to the proof being highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    Plain,
+    // On the path from the proven leaf to the root.
+    Path,
+    // A sibling of the path, i.e. a hash the proof carries.
+    Sibling,
+}
+
+// A node as drawn. The copy of a node paired with itself is drawn as its
+// own, childless node.
+#[derive(Debug, Clone, Copy)]
+struct Drawn {
+    id: NodeId,
+    duplicate: bool,
+    role: Role,
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    // Graphviz source for the tree. Nodes on the path of `proof` are filled
+    // gold and the hashes the proof carries light blue. A node paired with
+    // itself is drawn again, dashed, as the right child of its parent. An
+    // empty tree is an empty graph.
+    pub fn to_dot(&self, proof: Option<&MerkleProof<H>>) -> String {
+        let mut dot = String::from("digraph merkle {\n    node [shape=box, fontname=monospace];\n");
+        if self.leaf_count > 0 {
+            self.dot_node(self.drawn_root(proof), proof, &mut dot);
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    // The tree drawn for a terminal, root first. Nodes on the path of
+    // `proof` end with `*` and the hashes the proof carries with `+`. An
+    // empty tree draws nothing.
+    pub fn to_ascii(&self, proof: Option<&MerkleProof<H>>) -> String {
+        let mut ascii = String::new();
+        if self.leaf_count > 0 {
+            self.ascii_node(self.drawn_root(proof), proof, "", "", &mut ascii);
+        }
+        ascii
+    }
+
+    fn drawn_root(&self, proof: Option<&MerkleProof<H>>) -> Drawn {
+        let id = self.root_id();
+        Drawn {
+            id,
+            duplicate: false,
+            role: self.role(id, proof),
+        }
+    }
+
+    fn dot_node(&self, node: Drawn, proof: Option<&MerkleProof<H>>, dot: &mut String) {
+        let name = Self::dot_name(node);
+        let mut attributes = format!("label=\"{}\"", self.label(node));
+        let fill = match node.role {
+            Role::Plain => None,
+            Role::Path => Some("gold"),
+            Role::Sibling => Some("lightblue"),
+        };
+        match (fill, node.duplicate) {
+            (None, false) => {}
+            (None, true) => attributes.push_str(", style=dashed"),
+            (Some(fill), false) => {
+                let _ = write!(attributes, ", style=filled, fillcolor={fill}");
+            }
+            (Some(fill), true) => {
+                let _ = write!(attributes, ", style=\"filled,dashed\", fillcolor={fill}");
+            }
+        }
+        let _ = writeln!(dot, "    {name} [{attributes}];");
+
+        for child in self.drawn_children(node, proof) {
+            let _ = writeln!(dot, "    {name} -> {};", Self::dot_name(child));
+            self.dot_node(child, proof, dot);
+        }
+    }
+
+    fn ascii_node(
+        &self,
+        node: Drawn,
+        proof: Option<&MerkleProof<H>>,
+        prefix: &str,
+        connector: &str,
+        ascii: &mut String,
+    ) {
+        let marker = match node.role {
+            Role::Plain => "",
+            Role::Path => " *",
+            Role::Sibling => " +",
+        };
+        let _ = writeln!(ascii, "{prefix}{connector}{}{marker}", self.label(node));
+
+        let prefix = match connector {
+            "├── " => format!("{prefix}│   "),
+            "└── " => format!("{prefix}    "),
+            _ => prefix.to_string(),
+        };
+        for (i, child) in self.drawn_children(node, proof).into_iter().enumerate() {
+            let connector = if i == 0 { "├── " } else { "└── " };
+            self.ascii_node(child, proof, &prefix, connector, ascii);
+        }
+    }
+
+    fn drawn_children(&self, node: Drawn, proof: Option<&MerkleProof<H>>) -> Vec<Drawn> {
+        if node.duplicate {
+            return Vec::new();
+        }
+        let Some((left, right)) = self.children(node.id) else {
+            return Vec::new();
+        };
+        [(left, false), (right, left == right)]
+            .into_iter()
+            .map(|(id, duplicate)| {
+                let role = match (node.role, self.role(id, proof)) {
+                    (_, Role::Path) if !duplicate => Role::Path,
+                    (Role::Path, _) => Role::Sibling,
+                    _ => Role::Plain,
+                };
+                Drawn {
+                    id,
+                    duplicate,
+                    role,
+                }
+            })
+            .collect()
+    }
+
+    // Only tells path nodes apart. Siblings are known from the parent.
+    fn role(&self, id: NodeId, proof: Option<&MerkleProof<H>>) -> Role {
+        match proof {
+            Some(proof)
+                if proof.index() < self.leaf_count && proof.index() >> id.level == id.index =>
+            {
+                Role::Path
+            }
+            _ => Role::Plain,
+        }
+    }
+
+    fn label(&self, node: Drawn) -> String {
+        let hash = short_hash(self.node(node.id));
+        let label = match node.id.level {
+            0 => format!("#{} {hash}", node.id.index),
+            _ => hash,
+        };
+        if node.duplicate {
+            label + " (duplicate)"
+        } else {
+            label
+        }
+    }
+
+    fn dot_name(node: Drawn) -> String {
+        let name = format!("n{}_{}", node.id.level, node.id.index);
+        if node.duplicate {
+            name + "_dup"
+        } else {
+            name
+        }
+    }
+}
+
+fn short_hash(hash: &Hash) -> String {
+    hash.to_hex()[..HASH_DIGITS].to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::Sha256;
+    use crate::merkle::{test_leaves as leaves, HashMode, TreeShape};
+
+    #[test]
+    fn test_draws_ascii_tree_with_duplicate() {
+        let leaves = leaves(3);
+        let tree = MerkleTree::new(&leaves);
+        let left = HashMode::Bytes.hash_nodes::<Sha256>(&leaves[0], &leaves[1]);
+        let right = HashMode::Bytes.hash_nodes::<Sha256>(&leaves[2], &leaves[2]);
+
+        let expected = format!(
+            "{}\n├── {}\n│   ├── #0 {}\n│   └── #1 {}\n└── {}\n    ├── #2 {}\n    └── #2 {} (duplicate)\n",
+            short_hash(&tree.hash()),
+            short_hash(&left),
+            short_hash(&leaves[0]),
+            short_hash(&leaves[1]),
+            short_hash(&right),
+            short_hash(&leaves[2]),
+            short_hash(&leaves[2]),
+        );
+        assert_eq!(tree.to_ascii(None), expected);
+    }
+
+    #[test]
+    fn test_marks_proof_path_and_siblings() {
+        let tree = MerkleTree::new(leaves(3));
+        let proof = tree.proof(2).unwrap();
+
+        let ascii = tree.to_ascii(Some(&proof));
+        let marked: Vec<&str> = ascii
+            .lines()
+            .filter(|line| line.ends_with(" *") || line.ends_with(" +"))
+            .collect();
+
+        // The root, the path down to leaf 2, and one line per proof step.
+        assert_eq!(marked.len(), 1 + 2 + proof.steps().len());
+        assert!(ascii
+            .lines()
+            .any(|line| line.contains("#2") && line.ends_with(" *")));
+        assert!(ascii
+            .lines()
+            .any(|line| line.contains("(duplicate)") && line.ends_with(" +")));
+        assert!(ascii.lines().next().unwrap().ends_with(" *"));
+    }
+
+    #[test]
+    fn test_draws_rfc6962_tree_without_promoted_copies() {
+        let tree = MerkleTree::<Sha256>::with_shape(leaves(3), HashMode::Bytes, TreeShape::Rfc6962);
+
+        let ascii = tree.to_ascii(None);
+
+        assert_eq!(ascii.lines().count(), 5);
+        assert!(!ascii.contains("duplicate"));
+    }
+
+    #[test]
+    fn test_draws_empty_tree() {
+        let tree = MerkleTree::new(leaves(0));
+
+        assert_eq!(tree.to_ascii(None), "");
+        assert_eq!(
+            tree.to_dot(None),
+            "digraph merkle {\n    node [shape=box, fontname=monospace];\n}\n"
+        );
+    }
+
+    #[test]
+    fn test_writes_dot_graph() {
+        let tree = MerkleTree::new(leaves(3));
+        let proof = tree.proof(0).unwrap();
+
+        let dot = tree.to_dot(Some(&proof));
+
+        assert!(dot.starts_with("digraph merkle {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert!(dot.contains("    n2_0 -> n1_1;\n"));
+        assert!(dot.contains("    n1_1 -> n0_2_dup;\n"));
+        assert!(dot.contains(&format!(
+            "    n0_0 [label=\"#0 {}\", style=filled, fillcolor=gold];\n",
+            short_hash(&tree.level(0)[0])
+        )));
+        assert_eq!(
+            dot.matches("fillcolor=lightblue").count(),
+            proof.steps().len()
+        );
+        assert_eq!(dot.matches("style=dashed").count(), 1);
+    }
+}
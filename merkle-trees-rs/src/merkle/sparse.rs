@@ -0,0 +1,464 @@
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
+
+use super::MerkleLeaf;
+
+const DEPTH: usize = 256;
+const LEAF_PREFIX: u8 = 0x00;
+
+// Key-value commitment over all 2^256 keys. Only non-empty nodes are stored;
+// every other node is the precomputed hash of an empty subtree of its height.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SparseMerkleTree<H: Hasher = Sha256> {
+    leaves: HashMap<[u8; 32], Hash>,
+    nodes: HashMap<(usize, [u8; 32]), Hash>,
+    empty: Vec<Hash>,
+    hasher: PhantomData<H>,
+}
+
+impl SparseMerkleTree {
+    pub fn new() -> Self {
+        Self::with_hasher()
+    }
+}
+
+impl Default for SparseMerkleTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher> SparseMerkleTree<H> {
+    pub fn with_hasher() -> Self {
+        Self {
+            leaves: HashMap::new(),
+            nodes: HashMap::new(),
+            empty: empty_hashes::<H>(),
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.leaves.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.leaves.is_empty()
+    }
+
+    pub fn root(&self) -> Hash {
+        self.node(0, &[0u8; 32])
+    }
+
+    pub fn get(&self, key: &Hash) -> Option<&Hash> {
+        self.leaves.get(&key.to_bytes())
+    }
+
+    // Returns the previous leaf hash when the key was already set.
+    pub fn insert<L: MerkleLeaf + ?Sized>(&mut self, key: &Hash, value: &L) -> Option<Hash> {
+        let leaf = value.leaf_hash::<H>();
+        let previous = self.leaves.insert(key.to_bytes(), leaf);
+        self.update_path(key, leaf_node::<H>(&leaf));
+        previous
+    }
+
+    pub fn remove(&mut self, key: &Hash) -> Option<Hash> {
+        let previous = self.leaves.remove(&key.to_bytes())?;
+        self.update_path(key, self.empty[0]);
+        Some(previous)
+    }
+
+    pub fn proof(&self, key: &Hash) -> SparseMerkleProof<H> {
+        let key = key.to_bytes();
+        let mut bitmap = [0u8; 32];
+        let mut siblings = Vec::new();
+
+        for depth in (1..=DEPTH).rev() {
+            let sibling = flip_bit(&prefix(&key, depth), depth - 1);
+            if let Some(hash) = self.nodes.get(&(depth, sibling)) {
+                set_bit(&mut bitmap, depth - 1);
+                siblings.push(*hash);
+            }
+        }
+
+        SparseMerkleProof::new(Hash::new(key), bitmap, siblings)
+    }
+
+    fn node(&self, depth: usize, prefix: &[u8; 32]) -> Hash {
+        self.nodes
+            .get(&(depth, *prefix))
+            .cloned()
+            .unwrap_or_else(|| self.empty[DEPTH - depth])
+    }
+
+    fn set_node(&mut self, depth: usize, prefix: [u8; 32], hash: Hash) {
+        if hash == self.empty[DEPTH - depth] {
+            self.nodes.remove(&(depth, prefix));
+        } else {
+            self.nodes.insert((depth, prefix), hash);
+        }
+    }
+
+    // Recomputes the 256 nodes between the leaf and the root.
+    fn update_path(&mut self, key: &Hash, leaf: Hash) {
+        let key = key.to_bytes();
+        let mut hash = leaf;
+        self.set_node(DEPTH, key, hash);
+
+        for depth in (1..=DEPTH).rev() {
+            let node = prefix(&key, depth);
+            let sibling = self.node(depth, &flip_bit(&node, depth - 1));
+            hash = if bit(&key, depth - 1) {
+                hash_nodes::<H>(&sibling, &hash)
+            } else {
+                hash_nodes::<H>(&hash, &sibling)
+            };
+            self.set_node(depth - 1, prefix(&key, depth - 1), hash);
+        }
+    }
+}
+
+// Inclusion or non-inclusion proof for one key. Siblings that are empty
+// subtrees are left out and flagged as such in the bitmap.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SparseMerkleProof<H: Hasher = Sha256> {
+    key: Hash,
+    bitmap: [u8; 32],
+    siblings: Vec<Hash>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    hasher: PhantomData<H>,
+}
+
+impl<H: Hasher> SparseMerkleProof<H> {
+    pub fn new(key: Hash, bitmap: [u8; 32], siblings: Vec<Hash>) -> Self {
+        Self {
+            key,
+            bitmap,
+            siblings,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn key(&self) -> &Hash {
+        &self.key
+    }
+
+    pub fn bitmap(&self) -> &[u8; 32] {
+        &self.bitmap
+    }
+
+    // Only the non-empty siblings, from the leaf up.
+    pub fn siblings(&self) -> &[Hash] {
+        &self.siblings
+    }
+
+    // The key is the one being queried, so a proof for another key is rejected.
+    pub fn verify_inclusion<L: MerkleLeaf + ?Sized>(
+        &self,
+        root: &Hash,
+        key: &Hash,
+        value: &L,
+    ) -> bool {
+        self.key == *key
+            && self.root(leaf_node::<H>(&value.leaf_hash::<H>())).as_ref() == Some(root)
+    }
+
+    pub fn verify_exclusion(&self, root: &Hash, key: &Hash) -> bool {
+        self.key == *key && self.root(Hash::default()).as_ref() == Some(root)
+    }
+
+    fn root(&self, leaf: Hash) -> Option<Hash> {
+        let key = self.key.to_bytes();
+        let mut siblings = self.siblings.iter();
+        let mut empty = Hash::default();
+        let mut hash = leaf;
+
+        for depth in (1..=DEPTH).rev() {
+            let sibling = if bit(&self.bitmap, depth - 1) {
+                *siblings.next()?
+            } else {
+                empty
+            };
+            hash = if bit(&key, depth - 1) {
+                hash_nodes::<H>(&sibling, &hash)
+            } else {
+                hash_nodes::<H>(&hash, &sibling)
+            };
+            empty = hash_nodes::<H>(&empty, &empty);
+        }
+
+        siblings.next().is_none().then_some(hash)
+    }
+}
+
+// empty[height] is the root of an empty subtree with 2^height leaves.
+fn empty_hashes<H: Hasher>() -> Vec<Hash> {
+    let mut empty = vec![Hash::default()];
+    for height in 0..DEPTH {
+        empty.push(hash_nodes::<H>(&empty[height], &empty[height]));
+    }
+    empty
+}
+
+// What a set key holds in the tree. The prefix keeps it from ever being the
+// all-zero hash that marks an empty leaf, whatever the value.
+fn leaf_node<H: Hasher>(leaf: &Hash) -> Hash {
+    H::hash(&[&[LEAF_PREFIX][..], &leaf.to_bytes()].concat())
+}
+
+fn hash_nodes<H: Hasher>(left: &Hash, right: &Hash) -> Hash {
+    H::hash(&[left.to_bytes(), right.to_bytes()].concat())
+}
+
+// Bit 0 is the most significant bit of the first byte, i.e. the branch taken
+// at the root.
+fn bit(bytes: &[u8; 32], index: usize) -> bool {
+    bytes[index / 8] & (0x80 >> (index % 8)) != 0
+}
+
+fn set_bit(bytes: &mut [u8; 32], index: usize) {
+    bytes[index / 8] |= 0x80 >> (index % 8);
+}
+
+fn flip_bit(bytes: &[u8; 32], index: usize) -> [u8; 32] {
+    let mut bytes = *bytes;
+    bytes[index / 8] ^= 0x80 >> (index % 8);
+    bytes
+}
+
+// The key with every bit below `depth` cleared, identifying its ancestor at
+// that depth.
+fn prefix(key: &[u8; 32], depth: usize) -> [u8; 32] {
+    let mut prefix = [0u8; 32];
+    let full_bytes = depth / 8;
+    prefix[..full_bytes].copy_from_slice(&key[..full_bytes]);
+    if !depth.is_multiple_of(8) {
+        prefix[full_bytes] = key[full_bytes] & !(0xff >> (depth % 8));
+    }
+    prefix
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(name: &str) -> Hash {
+        Hash::from_bytes(name.as_bytes())
+    }
+
+    fn balance(amount: u64) -> Vec<u8> {
+        amount.to_le_bytes().to_vec()
+    }
+
+    // Recomputes the root from scratch by splitting the leaves on each bit.
+    fn naive_root(leaves: &[([u8; 32], Hash)], depth: usize, empty: &[Hash]) -> Hash {
+        if leaves.is_empty() {
+            return empty[DEPTH - depth];
+        }
+        if depth == DEPTH {
+            return leaf_node::<Sha256>(&leaves[0].1);
+        }
+        let (right, left): (Vec<_>, Vec<_>) =
+            leaves.iter().cloned().partition(|(key, _)| bit(key, depth));
+        hash_nodes::<Sha256>(
+            &naive_root(&left, depth + 1, empty),
+            &naive_root(&right, depth + 1, empty),
+        )
+    }
+
+    #[test]
+    fn test_empty_tree_root_is_empty_subtree_hash() {
+        let tree = SparseMerkleTree::new();
+
+        assert!(tree.is_empty());
+        assert_eq!(tree.root(), empty_hashes::<Sha256>()[DEPTH]);
+        assert_eq!(tree.root(), naive_root(&[], 0, &empty_hashes::<Sha256>()));
+    }
+
+    #[test]
+    fn test_inserts_values() {
+        let mut tree = SparseMerkleTree::new();
+        let empty_root = tree.root();
+
+        assert_eq!(tree.insert(&key("alice"), &balance(10)), None);
+        assert_eq!(tree.insert(&key("bob"), &balance(20)), None);
+
+        assert_eq!(tree.len(), 2);
+        assert_ne!(tree.root(), empty_root);
+        assert_eq!(
+            tree.get(&key("alice")),
+            Some(&Hash::from_bytes(&balance(10)))
+        );
+        assert_eq!(tree.get(&key("carol")), None);
+    }
+
+    #[test]
+    fn test_matches_naive_root() {
+        let mut tree = SparseMerkleTree::new();
+        let mut leaves = Vec::new();
+        for i in 0..20 {
+            let key = key(&format!("account {i}"));
+            tree.insert(&key, &balance(i));
+            leaves.push((key.to_bytes(), Hash::from_bytes(&balance(i))));
+        }
+
+        assert_eq!(
+            tree.root(),
+            naive_root(&leaves, 0, &empty_hashes::<Sha256>())
+        );
+    }
+
+    #[test]
+    fn test_root_does_not_depend_on_insertion_order() {
+        let mut tree = SparseMerkleTree::new();
+        let mut other = SparseMerkleTree::new();
+        for name in ["alice", "bob", "carol"] {
+            tree.insert(&key(name), name.as_bytes());
+        }
+        for name in ["carol", "alice", "bob"] {
+            other.insert(&key(name), name.as_bytes());
+        }
+
+        assert_eq!(tree.root(), other.root());
+    }
+
+    #[test]
+    fn test_updates_value() {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(&key("alice"), &balance(10));
+        let root = tree.root();
+
+        let previous = tree.insert(&key("alice"), &balance(15));
+
+        assert_eq!(previous, Some(Hash::from_bytes(&balance(10))));
+        assert_ne!(tree.root(), root);
+        tree.insert(&key("alice"), &balance(10));
+        assert_eq!(tree.root(), root);
+    }
+
+    #[test]
+    fn test_removes_value() {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(&key("alice"), &balance(10));
+        let root = tree.root();
+        tree.insert(&key("bob"), &balance(20));
+
+        assert_eq!(
+            tree.remove(&key("bob")),
+            Some(Hash::from_bytes(&balance(20)))
+        );
+        assert_eq!(tree.remove(&key("bob")), None);
+        assert_eq!(tree.root(), root);
+
+        tree.remove(&key("alice"));
+        assert_eq!(tree.root(), SparseMerkleTree::new().root());
+        assert!(tree.nodes.is_empty());
+    }
+
+    #[test]
+    fn test_verifies_inclusion_proof() {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(&key("alice"), &balance(10));
+        tree.insert(&key("bob"), &balance(20));
+
+        let proof = tree.proof(&key("alice"));
+
+        assert!(proof.verify_inclusion(&tree.root(), &key("alice"), &balance(10)));
+        assert!(!proof.verify_inclusion(&tree.root(), &key("alice"), &balance(11)));
+        assert!(!proof.verify_exclusion(&tree.root(), &key("alice")));
+    }
+
+    #[test]
+    fn test_verifies_non_inclusion_proof() {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(&key("alice"), &balance(10));
+        tree.insert(&key("bob"), &balance(20));
+
+        let proof = tree.proof(&key("carol"));
+
+        assert!(proof.verify_exclusion(&tree.root(), &key("carol")));
+        assert!(!proof.verify_inclusion(&tree.root(), &key("carol"), &balance(0)));
+    }
+
+    #[test]
+    fn test_rejects_proof_for_other_key() {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(&key("alice"), &balance(10));
+
+        let absent = tree.proof(&key("carol"));
+        let present = tree.proof(&key("alice"));
+
+        assert!(!absent.verify_exclusion(&tree.root(), &key("alice")));
+        assert!(!absent.verify_exclusion(&tree.root(), &key("dave")));
+        assert!(!present.verify_inclusion(&tree.root(), &key("bob"), &balance(10)));
+    }
+
+    #[test]
+    fn test_value_hashing_to_zero_is_not_empty() {
+        let mut tree = SparseMerkleTree::new();
+        let empty_root = tree.root();
+
+        // A Hash is its own leaf hash, so this leaf hash is all zeros.
+        tree.insert(&key("alice"), &Hash::default());
+        let proof = tree.proof(&key("alice"));
+
+        assert_eq!(tree.len(), 1);
+        assert_ne!(tree.root(), empty_root);
+        assert!(proof.verify_inclusion(&tree.root(), &key("alice"), &Hash::default()));
+        assert!(!proof.verify_exclusion(&tree.root(), &key("alice")));
+    }
+
+    #[test]
+    fn test_verifies_non_inclusion_in_empty_tree() {
+        let tree = SparseMerkleTree::new();
+        let proof = tree.proof(&key("alice"));
+
+        assert!(proof.siblings().is_empty());
+        assert!(proof.verify_exclusion(&tree.root(), &key("alice")));
+    }
+
+    #[test]
+    fn test_skips_empty_siblings() {
+        let mut tree = SparseMerkleTree::new();
+        for i in 0..8 {
+            tree.insert(&key(&format!("account {i}")), &balance(i));
+        }
+        let proof = tree.proof(&key("account 3"));
+
+        // With 8 random keys only the top few levels have non-empty siblings.
+        assert!(proof.siblings().len() < 8);
+        let flagged: u32 = proof.bitmap().iter().map(|byte| byte.count_ones()).sum();
+        assert_eq!(flagged as usize, proof.siblings().len());
+        assert!(proof.verify_inclusion(&tree.root(), &key("account 3"), &balance(3)));
+    }
+
+    #[test]
+    fn test_rejects_tampered_key_and_bitmap() {
+        let mut tree = SparseMerkleTree::new();
+        tree.insert(&key("alice"), &balance(10));
+        tree.insert(&key("bob"), &balance(20));
+        let proof = tree.proof(&key("alice"));
+
+        // The same siblings one level lower.
+        let mut bitmap = [0u8; 32];
+        for index in 0..DEPTH - 1 {
+            if bit(proof.bitmap(), index) {
+                set_bit(&mut bitmap, index + 1);
+            }
+        }
+        let shifted =
+            SparseMerkleProof::<Sha256>::new(*proof.key(), bitmap, proof.siblings().to_vec());
+        assert!(!shifted.verify_inclusion(&tree.root(), &key("alice"), &balance(10)));
+
+        let other_key = SparseMerkleProof::<Sha256>::new(
+            key("carol"),
+            *proof.bitmap(),
+            proof.siblings().to_vec(),
+        );
+        assert!(!other_key.verify_inclusion(&tree.root(), &key("carol"), &balance(10)));
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt::Display;
+use std::io;
+
+use crate::codec::DecodeError;
+
+#[derive(Debug)]
+pub enum SyncError {
+    Io(io::Error),
+    InvalidMessage,
+    // The peer answered something other than what was asked.
+    UnexpectedResponse,
+    // The peer's tree has more leaves than the session allows.
+    TooManyLeaves(usize),
+}
+
+impl From<io::Error> for SyncError {
+    fn from(error: io::Error) -> Self {
+        SyncError::Io(error)
+    }
+}
+
+// Every way a message can fail to decode is a malformed message.
+impl From<DecodeError> for SyncError {
+    fn from(_: DecodeError) -> Self {
+        SyncError::InvalidMessage
+    }
+}
+
+impl Display for SyncError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SyncError::Io(error) => write!(f, "{error}"),
+            SyncError::InvalidMessage => write!(f, "malformed sync message"),
+            SyncError::UnexpectedResponse => write!(f, "response does not match the request"),
+            SyncError::TooManyLeaves(count) => write!(f, "peer has too many leaves ({count})"),
+        }
+    }
+}
+
+impl std::error::Error for SyncError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            SyncError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
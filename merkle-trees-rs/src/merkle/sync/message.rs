@@ -0,0 +1,178 @@
+use crate::codec::Decoder;
+use crate::hash::Hash;
+
+use super::SyncError;
+
+// Nodes are addressed by (level, index), level 0 being the leaves. Node
+// (level, index) covers leaves index << level up to (index + 1) << level in
+// every tree, whatever its size.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncRequest {
+    Summary,
+    Nodes(Vec<(usize, usize)>),
+}
+
+// Answers a request in the order it asked. A node the tree doesn't have is
+// None, and the root of an empty tree is all zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyncResponse {
+    Summary { leaf_count: usize, root: Hash },
+    Nodes(Vec<Option<Hash>>),
+}
+
+const SUMMARY: u8 = 0;
+const NODES: u8 = 1;
+
+impl SyncRequest {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SyncRequest::Summary => vec![SUMMARY],
+            SyncRequest::Nodes(nodes) => {
+                let mut bytes = vec![NODES];
+                bytes.extend((nodes.len() as u64).to_le_bytes());
+                for &(level, index) in nodes {
+                    bytes.push(level as u8);
+                    bytes.extend((index as u64).to_le_bytes());
+                }
+                bytes
+            }
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SyncError> {
+        let mut decoder = Decoder::new(bytes);
+        let request = match decoder.u8()? {
+            SUMMARY => SyncRequest::Summary,
+            NODES => {
+                let count = decoder.count(9)?;
+                let mut nodes = Vec::with_capacity(count);
+                for _ in 0..count {
+                    nodes.push((decoder.u8()? as usize, read_usize(&mut decoder)?));
+                }
+                SyncRequest::Nodes(nodes)
+            }
+            _ => return Err(SyncError::InvalidMessage),
+        };
+        decoder.finish()?;
+        Ok(request)
+    }
+}
+
+impl SyncResponse {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self {
+            SyncResponse::Summary { leaf_count, root } => {
+                let mut bytes = vec![SUMMARY];
+                bytes.extend((*leaf_count as u64).to_le_bytes());
+                bytes.extend(root.to_bytes());
+                bytes
+            }
+            SyncResponse::Nodes(hashes) => {
+                let mut bytes = vec![NODES];
+                bytes.extend((hashes.len() as u64).to_le_bytes());
+                for hash in hashes {
+                    match hash {
+                        Some(hash) => {
+                            bytes.push(1);
+                            bytes.extend(hash.to_bytes());
+                        }
+                        None => bytes.push(0),
+                    }
+                }
+                bytes
+            }
+        }
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, SyncError> {
+        let mut decoder = Decoder::new(bytes);
+        let response = match decoder.u8()? {
+            SUMMARY => SyncResponse::Summary {
+                leaf_count: read_usize(&mut decoder)?,
+                root: decoder.hash()?,
+            },
+            NODES => {
+                let count = decoder.count(1)?;
+                let mut hashes = Vec::with_capacity(count);
+                for _ in 0..count {
+                    hashes.push(match decoder.u8()? {
+                        0 => None,
+                        1 => Some(decoder.hash()?),
+                        _ => return Err(SyncError::InvalidMessage),
+                    });
+                }
+                SyncResponse::Nodes(hashes)
+            }
+            _ => return Err(SyncError::InvalidMessage),
+        };
+        decoder.finish()?;
+        Ok(response)
+    }
+}
+
+// Leaf counts and indices are written as u64s.
+fn read_usize(decoder: &mut Decoder) -> Result<usize, SyncError> {
+    usize::try_from(decoder.u64()?).map_err(|_| SyncError::InvalidMessage)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decodes_what_it_encodes() {
+        let requests = [
+            SyncRequest::Summary,
+            SyncRequest::Nodes(vec![]),
+            SyncRequest::Nodes(vec![(3, 0), (0, 12)]),
+        ];
+        for request in requests {
+            assert_eq!(
+                SyncRequest::from_bytes(&request.to_bytes()).unwrap(),
+                request
+            );
+        }
+
+        let responses = [
+            SyncResponse::Summary {
+                leaf_count: 5,
+                root: Hash::from_bytes(b"root"),
+            },
+            SyncResponse::Nodes(vec![Some(Hash::from_bytes(b"node")), None]),
+        ];
+        for response in responses {
+            assert_eq!(
+                SyncResponse::from_bytes(&response.to_bytes()).unwrap(),
+                response
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_malformed_messages() {
+        let request = SyncRequest::Nodes(vec![(1, 2)]).to_bytes();
+        let response = SyncResponse::Nodes(vec![Some(Hash::from_bytes(b"node"))]).to_bytes();
+
+        for bytes in [
+            &[][..],
+            &[2],
+            &request[..request.len() - 1],
+            &[&request[..], &[0]].concat(),
+        ] {
+            assert!(matches!(
+                SyncRequest::from_bytes(bytes),
+                Err(SyncError::InvalidMessage)
+            ));
+        }
+        let mut bad_flag = response.clone();
+        bad_flag[9] = 2;
+        let mut huge_count = response.clone();
+        huge_count[1..9].copy_from_slice(&u64::MAX.to_le_bytes());
+        for bytes in [&response[..response.len() - 1], &bad_flag, &huge_count] {
+            assert!(matches!(
+                SyncResponse::from_bytes(bytes),
+                Err(SyncError::InvalidMessage)
+            ));
+        }
+    }
+}
@@ -0,0 +1,365 @@
+mod error;
+mod message;
+
+pub use error::SyncError;
+pub use message::{SyncRequest, SyncResponse};
+
+use std::io::{self, Read, Write};
+use std::ops::Range;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
+
+use super::{tail, to_ranges, MerkleTree, NodeId};
+
+// The most leaves a session accepts from a peer unless told otherwise.
+pub const DEFAULT_MAX_LEAVES: usize = u32::MAX as usize;
+
+// The side of an anti-entropy exchange that wants to know how its tree
+// differs from a peer's, as in Cassandra or Dynamo repair. It asks for the
+// peer's root first, then one level at a time for the children of the nodes
+// that differ, so each round trip only carries hashes of changed subtrees.
+// Leaves only one side has are known to differ from the summary alone and
+// are never asked for.
+#[derive(Debug, Clone)]
+pub struct SyncSession<'a, H: Hasher = Sha256> {
+    tree: &'a MerkleTree<H>,
+    max_leaves: usize,
+    remote_leaves: Option<usize>,
+    pending: Vec<NodeId>,
+    differences: Vec<usize>,
+}
+
+impl<'a, H: Hasher> SyncSession<'a, H> {
+    pub fn new(tree: &'a MerkleTree<H>) -> Self {
+        Self::with_max_leaves(tree, DEFAULT_MAX_LEAVES)
+    }
+
+    // Fails the session if the peer claims more than `max_leaves` leaves.
+    pub fn with_max_leaves(tree: &'a MerkleTree<H>, max_leaves: usize) -> Self {
+        Self {
+            tree,
+            // No tree that fits in memory has more leaves.
+            max_leaves: max_leaves.min(isize::MAX as usize),
+            remote_leaves: None,
+            pending: Vec::new(),
+            differences: Vec::new(),
+        }
+    }
+
+    // The next request to send, or None once the differences are known.
+    pub fn request(&self) -> Option<SyncRequest> {
+        match self.remote_leaves {
+            None => Some(SyncRequest::Summary),
+            Some(_) if self.pending.is_empty() => None,
+            Some(_) => Some(SyncRequest::Nodes(
+                self.pending.iter().map(|id| (id.level, id.index)).collect(),
+            )),
+        }
+    }
+
+    pub fn receive(&mut self, response: SyncResponse) -> Result<(), SyncError> {
+        match (self.remote_leaves, response) {
+            (None, SyncResponse::Summary { leaf_count, root }) => {
+                if leaf_count > self.max_leaves {
+                    return Err(SyncError::TooManyLeaves(leaf_count));
+                }
+                self.remote_leaves = Some(leaf_count);
+                // The summary already answers for the node at the top.
+                let top = NodeId::top(self.tree.leaf_count.max(leaf_count));
+                let theirs = (NodeId::top(leaf_count) == top).then_some(&root);
+                self.visit(top, theirs, leaf_count);
+                Ok(())
+            }
+            (Some(remote_leaves), SyncResponse::Nodes(hashes))
+                if !self.pending.is_empty() && hashes.len() == self.pending.len() =>
+            {
+                for (id, theirs) in std::mem::take(&mut self.pending).into_iter().zip(&hashes) {
+                    self.visit(id, theirs.as_ref(), remote_leaves);
+                }
+                Ok(())
+            }
+            _ => Err(SyncError::UnexpectedResponse),
+        }
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.request().is_none()
+    }
+
+    // Leaves that differ from the peer's, including leaves only one of the
+    // trees has. Complete once the session is done.
+    pub fn ranges(&self) -> Vec<Range<usize>> {
+        let remote_leaves = self.remote_leaves.unwrap_or(self.tree.leaf_count);
+        to_ranges(&self.differences, tail(self.tree.leaf_count, remote_leaves))
+    }
+
+    fn visit(&mut self, id: NodeId, theirs: Option<&Hash>, remote_leaves: usize) {
+        if !self.tree.differs_at(id, theirs, remote_leaves) {
+            return;
+        }
+        if id.level == 0 {
+            self.differences.push(id.index);
+        } else {
+            self.pending.extend(id.children());
+        }
+    }
+}
+
+impl<H: Hasher> MerkleTree<H> {
+    pub fn respond(&self, request: &SyncRequest) -> SyncResponse {
+        match request {
+            // An empty tree has no root to send.
+            SyncRequest::Summary if self.leaf_count == 0 => SyncResponse::Summary {
+                leaf_count: 0,
+                root: Hash::default(),
+            },
+            SyncRequest::Summary => SyncResponse::Summary {
+                leaf_count: self.leaf_count,
+                root: self.hash(),
+            },
+            SyncRequest::Nodes(nodes) => SyncResponse::Nodes(
+                nodes
+                    .iter()
+                    .map(|&(level, index)| self.get_node(NodeId { level, index }).cloned())
+                    .collect(),
+            ),
+        }
+    }
+
+    // Runs a session against a peer serving its tree on `stream` and returns
+    // the leaf ranges that differ. Messages are framed with a u64 LE length.
+    pub fn sync_with<S: Read + Write>(&self, stream: S) -> Result<Vec<Range<usize>>, SyncError> {
+        self.sync_with_max_leaves(stream, DEFAULT_MAX_LEAVES)
+    }
+
+    pub fn sync_with_max_leaves<S: Read + Write>(
+        &self,
+        mut stream: S,
+        max_leaves: usize,
+    ) -> Result<Vec<Range<usize>>, SyncError> {
+        let mut session = SyncSession::with_max_leaves(self, max_leaves);
+        while let Some(request) = session.request() {
+            write_frame(&mut stream, &request.to_bytes())?;
+            let response = read_frame(&mut stream)?
+                .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))?;
+            session.receive(SyncResponse::from_bytes(&response)?)?;
+        }
+        Ok(session.ranges())
+    }
+
+    // Answers requests from `stream` until the peer closes it.
+    pub fn serve_sync<S: Read + Write>(&self, mut stream: S) -> Result<(), SyncError> {
+        while let Some(request) = read_frame(&mut stream)? {
+            let response = self.respond(&SyncRequest::from_bytes(&request)?);
+            write_frame(&mut stream, &response.to_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn write_frame<W: Write>(writer: &mut W, message: &[u8]) -> io::Result<()> {
+    writer.write_all(&(message.len() as u64).to_le_bytes())?;
+    writer.write_all(message)?;
+    writer.flush()
+}
+
+// None when the stream ends cleanly between frames.
+fn read_frame<R: Read>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0u8; 8];
+    let mut read = 0;
+    while read < length.len() {
+        match reader.read(&mut length[read..]) {
+            Ok(0) if read == 0 => return Ok(None),
+            Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+            Ok(n) => read += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+
+    // Grows with what actually arrives rather than trusting the length.
+    let length = u64::from_le_bytes(length);
+    let mut message = Vec::new();
+    reader.take(length).read_to_end(&mut message)?;
+    if (message.len() as u64) < length {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(Some(message))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::merkle::{test_leaves as leaves, HashMode, TreeShape};
+    use std::net::{TcpListener, TcpStream};
+    use std::thread;
+
+    // Runs a session in memory and counts the round trips.
+    fn reconcile(local: &MerkleTree, remote: &MerkleTree) -> (Vec<Range<usize>>, usize) {
+        let mut session = SyncSession::new(local);
+        let mut rounds = 0;
+        while let Some(request) = session.request() {
+            session.receive(remote.respond(&request)).unwrap();
+            rounds += 1;
+        }
+        (session.ranges(), rounds)
+    }
+
+    #[test]
+    fn test_finds_same_ranges_as_diff() {
+        for shape in [TreeShape::DuplicateLast, TreeShape::Rfc6962] {
+            for (local_size, remote_size) in [(16, 16), (10, 16), (16, 3), (1, 1), (1, 9)] {
+                let local = MerkleTree::with_shape(leaves(local_size), HashMode::Bytes, shape);
+                let mut changed = leaves(remote_size);
+                changed[remote_size / 3] = Hash::from_bytes(b"changed");
+                let remote = MerkleTree::with_shape(changed, HashMode::Bytes, shape);
+
+                let (ranges, _) = reconcile(&local, &remote);
+
+                assert_eq!(
+                    ranges,
+                    local.diff_ranges(&remote),
+                    "{local_size} -> {remote_size}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_syncs_with_empty_tree() {
+        let empty = MerkleTree::new(leaves(0));
+        let tree = MerkleTree::new(leaves(5));
+
+        assert_eq!(
+            empty.respond(&SyncRequest::Summary),
+            SyncResponse::Summary {
+                leaf_count: 0,
+                root: Hash::default()
+            }
+        );
+        assert_eq!(reconcile(&tree, &empty).0, vec![0..5]);
+        assert_eq!(reconcile(&empty, &tree).0, vec![0..5]);
+        assert_eq!(reconcile(&empty, &empty), (vec![], 1));
+    }
+
+    #[test]
+    fn test_equal_trees_take_one_round_trip() {
+        let tree = MerkleTree::new(leaves(1000));
+
+        assert_eq!(reconcile(&tree, &tree.clone()), (vec![], 1));
+    }
+
+    #[test]
+    fn test_descends_only_into_changed_subtrees() {
+        let local = MerkleTree::new(leaves(1024));
+        let mut changed = leaves(1024);
+        changed[700] = Hash::from_bytes(b"changed");
+        let remote = MerkleTree::new(changed);
+
+        let mut session = SyncSession::new(&local);
+        let mut requested = 0;
+        while let Some(request) = session.request() {
+            if let SyncRequest::Nodes(nodes) = &request {
+                requested += nodes.len();
+            }
+            session.receive(remote.respond(&request)).unwrap();
+        }
+
+        assert_eq!(session.ranges(), vec![700..701]);
+        // Both children at each of the ten levels below the root.
+        assert_eq!(requested, 20);
+    }
+
+    #[test]
+    fn test_reports_leaves_only_one_side_has_without_visiting_them() {
+        let local = MerkleTree::new(leaves(3));
+        let mut session = SyncSession::with_max_leaves(&local, usize::MAX);
+        let mut requested = 0;
+        assert!(matches!(
+            session.clone().receive(SyncResponse::Summary {
+                leaf_count: usize::MAX,
+                root: Hash::default(),
+            }),
+            Err(SyncError::TooManyLeaves(usize::MAX))
+        ));
+        session
+            .receive(SyncResponse::Summary {
+                leaf_count: 1 << 40,
+                root: Hash::from_bytes(b"huge"),
+            })
+            .unwrap();
+        while let Some(request) = session.request() {
+            let SyncRequest::Nodes(nodes) = request else {
+                unreachable!()
+            };
+            requested += nodes.len();
+            // The peer's first three leaves match ours.
+            let hashes = nodes
+                .iter()
+                .map(|&(level, index)| local.get_node(NodeId { level, index }).cloned())
+                .collect();
+            session.receive(SyncResponse::Nodes(hashes)).unwrap();
+        }
+
+        assert_eq!(session.ranges(), vec![3..1 << 40]);
+        // Two children at each of the forty levels, at most.
+        assert!(requested <= 80);
+    }
+
+    #[test]
+    fn test_rejects_peer_with_too_many_leaves() {
+        let tree = MerkleTree::new(leaves(4));
+        let mut session = SyncSession::with_max_leaves(&tree, 100);
+
+        assert!(matches!(
+            session.receive(SyncResponse::Summary {
+                leaf_count: 101,
+                root: Hash::default(),
+            }),
+            Err(SyncError::TooManyLeaves(101))
+        ));
+    }
+
+    #[test]
+    fn test_rejects_response_that_does_not_match_request() {
+        let tree = MerkleTree::new(leaves(4));
+        let mut session = SyncSession::new(&tree);
+
+        assert!(matches!(
+            session.receive(SyncResponse::Nodes(vec![])),
+            Err(SyncError::UnexpectedResponse)
+        ));
+        session
+            .receive(SyncResponse::Summary {
+                leaf_count: 4,
+                root: Hash::from_bytes(b"other"),
+            })
+            .unwrap();
+        assert!(matches!(
+            session.receive(SyncResponse::Nodes(vec![None])),
+            Err(SyncError::UnexpectedResponse)
+        ));
+    }
+
+    #[test]
+    fn test_syncs_over_a_stream() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let server = thread::spawn(move || {
+            let mut remote = leaves(100);
+            remote[5] = Hash::from_bytes(b"changed");
+            remote[6] = Hash::from_bytes(b"changed too");
+            let (stream, _) = listener.accept().unwrap();
+            MerkleTree::new(remote).serve_sync(stream).unwrap();
+        });
+
+        let local = MerkleTree::new(leaves(90));
+        let ranges = local
+            .sync_with(TcpStream::connect(address).unwrap())
+            .unwrap();
+        server.join().unwrap();
+
+        assert_eq!(ranges, vec![5..7, 90..100]);
+    }
+}
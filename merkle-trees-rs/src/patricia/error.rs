@@ -0,0 +1,22 @@
+use std::fmt::Display;
+
+use crate::hash::Hash;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrieError {
+    InvalidRlp,
+    InvalidNode,
+    MissingNode(Hash),
+}
+
+impl Display for TrieError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrieError::InvalidRlp => write!(f, "invalid RLP encoding"),
+            TrieError::InvalidNode => write!(f, "malformed trie node"),
+            TrieError::MissingNode(hash) => write!(f, "proof is missing trie node {hash}"),
+        }
+    }
+}
+
+impl std::error::Error for TrieError {}
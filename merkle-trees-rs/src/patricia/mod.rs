@@ -0,0 +1,573 @@
+mod error;
+mod nibbles;
+pub mod rlp;
+
+use std::collections::HashMap;
+use std::marker::PhantomData;
+
+use crate::hash::Hash;
+use crate::hasher::{Hasher, Keccak256};
+
+pub use error::TrieError;
+use nibbles::{common_prefix, decode_hex_prefix, encode_hex_prefix, to_nibbles};
+use rlp::Rlp;
+
+// Node of a modified Merkle Patricia trie. Paths are nibbles; an extension
+// always leads to a branch.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+enum Node {
+    #[default]
+    Empty,
+    Leaf {
+        path: Vec<u8>,
+        value: Vec<u8>,
+    },
+    Extension {
+        path: Vec<u8>,
+        child: Box<Node>,
+    },
+    Branch {
+        children: Box<[Node; 16]>,
+        value: Option<Vec<u8>>,
+    },
+}
+
+impl Node {
+    fn branch() -> Self {
+        Node::Branch {
+            children: Box::new(std::array::from_fn(|_| Node::Empty)),
+            value: None,
+        }
+    }
+
+    // An extension over `path`, merged into its child when that is not a
+    // branch.
+    fn extension(path: &[u8], child: Node) -> Self {
+        match child {
+            Node::Empty => Node::Empty,
+            Node::Leaf { path: rest, value } => Node::Leaf {
+                path: [path, &rest].concat(),
+                value,
+            },
+            Node::Extension { path: rest, child } => Node::Extension {
+                path: [path, &rest].concat(),
+                child,
+            },
+            branch if path.is_empty() => branch,
+            branch => Node::Extension {
+                path: path.to_vec(),
+                child: Box::new(branch),
+            },
+        }
+    }
+
+    fn get(&self, path: &[u8]) -> Option<&[u8]> {
+        match self {
+            Node::Empty => None,
+            Node::Leaf { path: leaf, value } => (leaf == path).then_some(value.as_slice()),
+            Node::Extension {
+                path: prefix,
+                child,
+            } => child.get(path.strip_prefix(prefix.as_slice())?),
+            Node::Branch { children, value } => match path.split_first() {
+                None => value.as_deref(),
+                Some((&nibble, rest)) => children[nibble as usize].get(rest),
+            },
+        }
+    }
+
+    fn insert(self, path: &[u8], new_value: Vec<u8>) -> Self {
+        match self {
+            Node::Empty => Node::Leaf {
+                path: path.to_vec(),
+                value: new_value,
+            },
+            Node::Leaf { path: leaf, value } => {
+                if leaf == path {
+                    return Node::Leaf {
+                        path: leaf,
+                        value: new_value,
+                    };
+                }
+                let shared = common_prefix(&leaf, path);
+                let branch = Node::branch()
+                    .insert(&leaf[shared..], value)
+                    .insert(&path[shared..], new_value);
+                Node::extension(&path[..shared], branch)
+            }
+            Node::Extension {
+                path: prefix,
+                child,
+            } => {
+                let shared = common_prefix(&prefix, path);
+                if shared == prefix.len() {
+                    let child = child.insert(&path[shared..], new_value);
+                    return Node::extension(&prefix, child);
+                }
+                let mut branch = Node::branch();
+                if let Node::Branch { children, .. } = &mut branch {
+                    children[prefix[shared] as usize] =
+                        Node::extension(&prefix[shared + 1..], *child);
+                }
+                let branch = branch.insert(&path[shared..], new_value);
+                Node::extension(&path[..shared], branch)
+            }
+            Node::Branch {
+                mut children,
+                mut value,
+            } => {
+                match path.split_first() {
+                    None => value = Some(new_value),
+                    Some((&nibble, rest)) => {
+                        let child = std::mem::take(&mut children[nibble as usize]);
+                        children[nibble as usize] = child.insert(rest, new_value);
+                    }
+                }
+                Node::Branch { children, value }
+            }
+        }
+    }
+
+    fn remove(self, path: &[u8]) -> Self {
+        match self {
+            Node::Leaf { path: leaf, .. } if leaf == path => Node::Empty,
+            Node::Extension {
+                path: prefix,
+                child,
+            } if path.starts_with(&prefix) => {
+                let child = child.remove(&path[prefix.len()..]);
+                Node::extension(&prefix, child)
+            }
+            Node::Branch {
+                mut children,
+                mut value,
+            } => {
+                match path.split_first() {
+                    None => value = None,
+                    Some((&nibble, rest)) => {
+                        let child = std::mem::take(&mut children[nibble as usize]);
+                        children[nibble as usize] = child.remove(rest);
+                    }
+                }
+                Node::collapse(children, value)
+            }
+            node => node,
+        }
+    }
+
+    // A branch left with a single child or only a value is replaced by a
+    // shorter node, so every trie has one canonical shape.
+    fn collapse(mut children: Box<[Node; 16]>, value: Option<Vec<u8>>) -> Self {
+        let used: Vec<usize> = (0..16).filter(|&i| children[i] != Node::Empty).collect();
+        match (used.as_slice(), value) {
+            ([], None) => Node::Empty,
+            ([], Some(value)) => Node::Leaf {
+                path: Vec::new(),
+                value,
+            },
+            (&[nibble], None) => {
+                let child = std::mem::take(&mut children[nibble]);
+                Node::extension(&[nibble as u8], child)
+            }
+            (_, value) => Node::Branch { children, value },
+        }
+    }
+
+    fn encode<H: Hasher>(&self) -> Vec<u8> {
+        match self {
+            Node::Empty => rlp::encode_bytes(&[]),
+            Node::Leaf { path, value } => rlp::encode_list(&[
+                rlp::encode_bytes(&encode_hex_prefix(path, true)),
+                rlp::encode_bytes(value),
+            ]),
+            Node::Extension { path, child } => rlp::encode_list(&[
+                rlp::encode_bytes(&encode_hex_prefix(path, false)),
+                child.reference::<H>(),
+            ]),
+            Node::Branch { children, value } => {
+                let mut items: Vec<Vec<u8>> = children
+                    .iter()
+                    .map(|child| child.reference::<H>())
+                    .collect();
+                items.push(rlp::encode_bytes(value.as_deref().unwrap_or_default()));
+                rlp::encode_list(&items)
+            }
+        }
+    }
+
+    // Nodes shorter than a hash are embedded in their parent.
+    fn reference<H: Hasher>(&self) -> Vec<u8> {
+        let encoded = self.encode::<H>();
+        if encoded.len() < 32 {
+            encoded
+        } else {
+            rlp::encode_bytes(&H::hash(&encoded).to_bytes())
+        }
+    }
+
+    // Pushes the encoding of every hashed node on the way to `path`.
+    fn proof<H: Hasher>(&self, path: &[u8], proof: &mut Vec<Vec<u8>>) {
+        let child = match self {
+            Node::Extension {
+                path: prefix,
+                child,
+            } => path
+                .strip_prefix(prefix.as_slice())
+                .map(|rest| (child.as_ref(), rest)),
+            Node::Branch { children, .. } => path
+                .split_first()
+                .map(|(&nibble, rest)| (&children[nibble as usize], rest)),
+            _ => None,
+        };
+        if let Some((child, rest)) = child {
+            let encoded = child.encode::<H>();
+            if encoded.len() >= 32 {
+                proof.push(encoded);
+            }
+            child.proof::<H>(rest, proof);
+        }
+    }
+}
+
+// Ethereum's modified Merkle Patricia trie, keyed by arbitrary bytes. Values
+// are opaque; inserting an empty value removes the key, as in Ethereum.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PatriciaTrie<H: Hasher = Keccak256> {
+    root: Node,
+    hasher: PhantomData<H>,
+}
+
+impl PatriciaTrie {
+    pub fn new() -> Self {
+        Self::with_hasher()
+    }
+}
+
+impl Default for PatriciaTrie {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<H: Hasher> PatriciaTrie<H> {
+    pub fn with_hasher() -> Self {
+        Self {
+            root: Node::Empty,
+            hasher: PhantomData,
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.root == Node::Empty
+    }
+
+    pub fn root(&self) -> Hash {
+        H::hash(&self.root.encode::<H>())
+    }
+
+    pub fn get(&self, key: &[u8]) -> Option<&[u8]> {
+        self.root.get(&to_nibbles(key))
+    }
+
+    pub fn insert(&mut self, key: &[u8], value: &[u8]) {
+        if value.is_empty() {
+            return self.remove(key);
+        }
+        let root = std::mem::take(&mut self.root);
+        self.root = root.insert(&to_nibbles(key), value.to_vec());
+    }
+
+    pub fn remove(&mut self, key: &[u8]) {
+        let root = std::mem::take(&mut self.root);
+        self.root = root.remove(&to_nibbles(key));
+    }
+
+    // The encoded nodes from the root towards `key`, in the format returned
+    // by eth_getProof. It proves the value or its absence.
+    pub fn proof(&self, key: &[u8]) -> Vec<Vec<u8>> {
+        let mut proof = vec![self.root.encode::<H>()];
+        self.root.proof::<H>(&to_nibbles(key), &mut proof);
+        proof
+    }
+}
+
+// Checks a proof against a state root without the trie. Returns the value
+// under `key`, or None when the proof shows the key is absent.
+pub fn verify_proof<H: Hasher>(
+    root: &Hash,
+    key: &[u8],
+    proof: &[Vec<u8>],
+) -> Result<Option<Vec<u8>>, TrieError> {
+    let nodes: HashMap<[u8; 32], &Vec<u8>> = proof
+        .iter()
+        .map(|node| (H::hash(node).to_bytes(), node))
+        .collect();
+    let resolve = |hash: &Hash| -> Result<Rlp, TrieError> {
+        let node = nodes
+            .get(&hash.to_bytes())
+            .ok_or(TrieError::MissingNode(*hash))?;
+        rlp::decode(node)
+    };
+
+    let path = to_nibbles(key);
+    let mut path = path.as_slice();
+    let mut node = resolve(root)?;
+    loop {
+        let child = match &node {
+            Rlp::Bytes(bytes) if bytes.is_empty() => return Ok(None),
+            Rlp::List(items) if items.len() == 2 => {
+                let Rlp::Bytes(prefix) = &items[0] else {
+                    return Err(TrieError::InvalidNode);
+                };
+                let (prefix, leaf) = decode_hex_prefix(prefix).ok_or(TrieError::InvalidNode)?;
+                if leaf {
+                    return match &items[1] {
+                        Rlp::Bytes(value) if path == prefix => Ok(Some(value.clone())),
+                        Rlp::Bytes(_) => Ok(None),
+                        Rlp::List(_) => Err(TrieError::InvalidNode),
+                    };
+                }
+                match path.strip_prefix(prefix.as_slice()) {
+                    Some(rest) => path = rest,
+                    None => return Ok(None),
+                }
+                items[1].clone()
+            }
+            Rlp::List(items) if items.len() == 17 => match path.split_first() {
+                None => {
+                    return match &items[16] {
+                        Rlp::Bytes(value) if value.is_empty() => Ok(None),
+                        Rlp::Bytes(value) => Ok(Some(value.clone())),
+                        Rlp::List(_) => Err(TrieError::InvalidNode),
+                    }
+                }
+                Some((&nibble, rest)) => {
+                    path = rest;
+                    items[nibble as usize].clone()
+                }
+            },
+            _ => return Err(TrieError::InvalidNode),
+        };
+
+        node = match child {
+            Rlp::Bytes(bytes) if bytes.is_empty() => return Ok(None),
+            Rlp::Bytes(bytes) => {
+                let hash: [u8; 32] = bytes.try_into().map_err(|_| TrieError::InvalidNode)?;
+                resolve(&Hash::new(hash))?
+            }
+            inline => inline,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hasher::Sha256;
+
+    fn trie(entries: &[(&str, &str)]) -> PatriciaTrie {
+        let mut trie = PatriciaTrie::new();
+        for (key, value) in entries {
+            trie.insert(&bytes(key), &bytes(value));
+        }
+        trie
+    }
+
+    // Fixture keys and values are either plain strings or 0x-prefixed hex.
+    fn bytes(value: &str) -> Vec<u8> {
+        match value.strip_prefix("0x") {
+            Some(hex) => (0..hex.len())
+                .step_by(2)
+                .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+                .collect(),
+            None => value.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn test_empty_trie_root() {
+        let trie = PatriciaTrie::new();
+
+        assert!(trie.is_empty());
+        assert_eq!(
+            trie.root(),
+            "56e81f171bcc55a6ff8345e692c0f86e5b48e01b996cadc001622fb5e363b421"
+        );
+    }
+
+    // Cases from ethereum/tests TrieTests/trieanyorder.json.
+    #[test]
+    fn test_matches_trie_any_order_fixtures() {
+        let a = "a".repeat(50);
+        let fixtures: [(&[(&str, &str)], &str); 7] = [
+            (
+                &[("A", &a)],
+                "d23786fb4a010da3ce639d66d5e904a11dbc02746d1ce25029e53290cabf28ab",
+            ),
+            (
+                &[
+                    ("doe", "reindeer"),
+                    ("dog", "puppy"),
+                    ("dogglesworth", "cat"),
+                ],
+                "8aad789dff2f538bca5d8ea56e8abe10f4c7ba3a5dea95fea4cd6e7c3a1168d3",
+            ),
+            (
+                &[
+                    ("do", "verb"),
+                    ("horse", "stallion"),
+                    ("doge", "coin"),
+                    ("dog", "puppy"),
+                ],
+                "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84",
+            ),
+            (
+                &[("foo", "bar"), ("food", "bass")],
+                "17beaa1648bafa633cda809c90c04af50fc8aed3cb40d16efbddee6fdf63c4c3",
+            ),
+            (
+                &[("be", "e"), ("dog", "puppy"), ("bed", "d")],
+                "3f67c7a47520f79faa29255d2d3c084a7a6df0453116ed7232ff10277a8be68b",
+            ),
+            (
+                &[("test", "test"), ("te", "testy")],
+                "8452568af70d8d140f58d941338542f645fcca50094b20f3c3d8c3df49337928",
+            ),
+            (
+                &[("0x0045", "0x0123456789"), ("0x4500", "0x9876543210")],
+                "285505fcabe84badc8aa310e2aae17eddc7d120aabec8a476902c8184b3a3503",
+            ),
+        ];
+
+        for (entries, root) in fixtures {
+            assert_eq!(trie(entries).root(), root);
+
+            let reversed: Vec<_> = entries.iter().rev().cloned().collect();
+            assert_eq!(trie(&reversed).root(), root);
+        }
+    }
+
+    // TrieTests/trietest.json "emptyValues": an empty value deletes the key.
+    #[test]
+    fn test_matches_empty_values_fixture() {
+        let trie = trie(&[
+            ("do", "verb"),
+            ("ether", "wookiedoo"),
+            ("horse", "stallion"),
+            ("shaman", "horse"),
+            ("doge", "coin"),
+            ("ether", ""),
+            ("dog", "puppy"),
+            ("shaman", ""),
+        ]);
+
+        assert_eq!(
+            trie.root(),
+            "5991bb8c6514148a29db676a14ac506cd2cd5775ace63c30a4fe457715e9ac84"
+        );
+    }
+
+    #[test]
+    fn test_gets_values() {
+        let trie = trie(&[("do", "verb"), ("dog", "puppy"), ("doge", "coin")]);
+
+        assert_eq!(trie.get(b"dog"), Some(&b"puppy"[..]));
+        assert_eq!(trie.get(b"do"), Some(&b"verb"[..]));
+        assert_eq!(trie.get(b"d"), None);
+        assert_eq!(trie.get(b"doges"), None);
+    }
+
+    #[test]
+    fn test_removing_every_key_restores_empty_root() {
+        let entries = [
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ];
+        let mut trie = trie(&entries);
+        let empty = PatriciaTrie::new().root();
+
+        for (i, (key, _)) in entries.iter().enumerate() {
+            trie.remove(key.as_bytes());
+            assert_eq!(trie.root(), self::trie(&entries[i + 1..]).root());
+        }
+        assert_eq!(trie.root(), empty);
+        assert!(trie.is_empty());
+    }
+
+    #[test]
+    fn test_verifies_inclusion_proofs() {
+        let entries = [
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ];
+        let trie = trie(&entries);
+        let root = trie.root();
+
+        for (key, value) in entries {
+            let proof = trie.proof(key.as_bytes());
+            assert_eq!(
+                verify_proof::<Keccak256>(&root, key.as_bytes(), &proof),
+                Ok(Some(value.as_bytes().to_vec()))
+            );
+        }
+    }
+
+    #[test]
+    fn test_verifies_exclusion_proofs() {
+        let trie = trie(&[
+            ("do", "verb"),
+            ("dog", "puppy"),
+            ("doge", "coin"),
+            ("horse", "stallion"),
+        ]);
+        let root = trie.root();
+
+        for key in ["d", "dogs", "cat", "horses", ""] {
+            let proof = trie.proof(key.as_bytes());
+            assert_eq!(
+                verify_proof::<Keccak256>(&root, key.as_bytes(), &proof),
+                Ok(None)
+            );
+        }
+
+        let empty = PatriciaTrie::new();
+        assert_eq!(
+            verify_proof::<Keccak256>(&empty.root(), b"dog", &empty.proof(b"dog")),
+            Ok(None)
+        );
+    }
+
+    #[test]
+    fn test_rejects_incomplete_proofs() {
+        let a = "a".repeat(40);
+        let trie = trie(&[("dog", &a), ("doge", &a), ("horse", &a)]);
+        let root = trie.root();
+        let proof = trie.proof(b"doge");
+        assert!(proof.len() > 1);
+
+        assert!(matches!(
+            verify_proof::<Keccak256>(&root, b"doge", &proof[..proof.len() - 1]),
+            Err(TrieError::MissingNode(_))
+        ));
+        assert!(matches!(
+            verify_proof::<Keccak256>(&Hash::default(), b"doge", &proof),
+            Err(TrieError::MissingNode(_))
+        ));
+    }
+
+    #[test]
+    fn test_roots_depend_on_hasher() {
+        let mut trie = PatriciaTrie::<Sha256>::with_hasher();
+        trie.insert(b"dog", b"puppy");
+
+        assert_ne!(trie.root(), self::trie(&[("dog", "puppy")]).root());
+        assert_eq!(
+            verify_proof::<Sha256>(&trie.root(), b"dog", &trie.proof(b"dog")),
+            Ok(Some(b"puppy".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,84 @@
+pub fn to_nibbles(bytes: &[u8]) -> Vec<u8> {
+    bytes.iter().flat_map(|b| [b >> 4, b & 0x0f]).collect()
+}
+
+// Hex-prefix encoding packs a nibble path into bytes. The first nibble
+// flags a leaf (2) and an odd length (1); even paths pad it with a zero.
+pub fn encode_hex_prefix(nibbles: &[u8], leaf: bool) -> Vec<u8> {
+    let flag = if leaf { 2 } else { 0 };
+    let (mut encoded, rest) = if nibbles.len() % 2 == 1 {
+        (vec![((flag + 1) << 4) | nibbles[0]], &nibbles[1..])
+    } else {
+        (vec![flag << 4], nibbles)
+    };
+    encoded.extend(rest.chunks(2).map(|pair| (pair[0] << 4) | pair[1]));
+    encoded
+}
+
+// Returns the path and whether it belongs to a leaf.
+pub fn decode_hex_prefix(bytes: &[u8]) -> Option<(Vec<u8>, bool)> {
+    let (&first, rest) = bytes.split_first()?;
+    let flag = first >> 4;
+    if flag > 3 {
+        return None;
+    }
+    let mut nibbles = Vec::with_capacity(rest.len() * 2 + 1);
+    if flag & 1 == 1 {
+        nibbles.push(first & 0x0f);
+    } else if first & 0x0f != 0 {
+        return None;
+    }
+    nibbles.extend(to_nibbles(rest));
+    Some((nibbles, flag & 2 == 2))
+}
+
+pub fn common_prefix(a: &[u8], b: &[u8]) -> usize {
+    a.iter().zip(b).take_while(|(a, b)| a == b).count()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodes_hex_prefix() {
+        assert_eq!(
+            encode_hex_prefix(&[1, 2, 3, 4, 5], false),
+            vec![0x11, 0x23, 0x45]
+        );
+        assert_eq!(
+            encode_hex_prefix(&[0, 1, 2, 3, 4, 5], false),
+            vec![0x00, 0x01, 0x23, 0x45]
+        );
+        assert_eq!(
+            encode_hex_prefix(&[0, 15, 1, 12, 11, 8], true),
+            vec![0x20, 0x0f, 0x1c, 0xb8]
+        );
+        assert_eq!(
+            encode_hex_prefix(&[15, 1, 12, 11, 8], true),
+            vec![0x3f, 0x1c, 0xb8]
+        );
+        assert_eq!(encode_hex_prefix(&[], true), vec![0x20]);
+    }
+
+    #[test]
+    fn test_decodes_hex_prefix() {
+        for nibbles in [vec![], vec![7], vec![1, 2], vec![0, 15, 1, 12, 11]] {
+            for leaf in [false, true] {
+                assert_eq!(
+                    decode_hex_prefix(&encode_hex_prefix(&nibbles, leaf)),
+                    Some((nibbles.clone(), leaf))
+                );
+            }
+        }
+        assert_eq!(decode_hex_prefix(&[]), None);
+        assert_eq!(decode_hex_prefix(&[0x40]), None);
+        assert_eq!(decode_hex_prefix(&[0x21]), None);
+    }
+
+    #[test]
+    fn test_splits_bytes_into_nibbles() {
+        assert_eq!(to_nibbles(b"\x12\xab"), vec![1, 2, 10, 11]);
+        assert_eq!(common_prefix(&[1, 2, 3], &[1, 2, 4, 5]), 2);
+    }
+}
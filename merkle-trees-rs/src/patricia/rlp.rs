@@ -0,0 +1,198 @@
+use super::TrieError;
+
+// Recursive Length Prefix, the serialisation Ethereum hashes trie nodes in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rlp {
+    Bytes(Vec<u8>),
+    List(Vec<Rlp>),
+}
+
+pub fn encode_bytes(bytes: &[u8]) -> Vec<u8> {
+    if bytes.len() == 1 && bytes[0] < 0x80 {
+        return bytes.to_vec();
+    }
+    let mut encoded = encode_length(bytes.len(), 0x80);
+    encoded.extend_from_slice(bytes);
+    encoded
+}
+
+// Takes items that are already encoded.
+pub fn encode_list(items: &[Vec<u8>]) -> Vec<u8> {
+    let payload = items.concat();
+    let mut encoded = encode_length(payload.len(), 0xc0);
+    encoded.extend(payload);
+    encoded
+}
+
+// Integers are big-endian without leading zeros, so zero is the empty string.
+pub fn encode_u64(value: u64) -> Vec<u8> {
+    let bytes = value.to_be_bytes();
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    encode_bytes(&bytes[start..])
+}
+
+fn encode_length(length: usize, offset: u8) -> Vec<u8> {
+    if length < 56 {
+        return vec![offset + length as u8];
+    }
+    let bytes = length.to_be_bytes();
+    let start = bytes.iter().position(|&b| b != 0).unwrap_or(bytes.len());
+    let mut encoded = vec![offset + 55 + (bytes.len() - start) as u8];
+    encoded.extend_from_slice(&bytes[start..]);
+    encoded
+}
+
+// Lists nested deeper than this are rejected rather than recursed into. A
+// trie node's inline children are under 32 bytes, so they never get close.
+const MAX_DEPTH: usize = 64;
+
+// Decodes exactly one item, rejecting trailing bytes and non-canonical
+// lengths so every value has a single encoding.
+pub fn decode(bytes: &[u8]) -> Result<Rlp, TrieError> {
+    let (item, rest) = decode_item(bytes, 0)?;
+    if !rest.is_empty() {
+        return Err(TrieError::InvalidRlp);
+    }
+    Ok(item)
+}
+
+pub fn decode_u64(item: &Rlp) -> Result<u64, TrieError> {
+    match item {
+        Rlp::Bytes(bytes) if bytes.len() <= 8 && bytes.first() != Some(&0) => {
+            Ok(bytes.iter().fold(0, |value, &b| (value << 8) | b as u64))
+        }
+        _ => Err(TrieError::InvalidRlp),
+    }
+}
+
+fn decode_item(bytes: &[u8], depth: usize) -> Result<(Rlp, &[u8]), TrieError> {
+    let (&prefix, rest) = bytes.split_first().ok_or(TrieError::InvalidRlp)?;
+    match prefix {
+        0x00..=0x7f => Ok((Rlp::Bytes(vec![prefix]), rest)),
+        0x80..=0xbf => {
+            let (payload, rest) = split_payload(prefix - 0x80, rest)?;
+            if payload.len() == 1 && payload[0] < 0x80 {
+                return Err(TrieError::InvalidRlp);
+            }
+            Ok((Rlp::Bytes(payload.to_vec()), rest))
+        }
+        0xc0..=0xff => {
+            if depth == MAX_DEPTH {
+                return Err(TrieError::InvalidRlp);
+            }
+            let (mut payload, rest) = split_payload(prefix - 0xc0, rest)?;
+            let mut items = Vec::new();
+            while !payload.is_empty() {
+                let (item, remaining) = decode_item(payload, depth + 1)?;
+                items.push(item);
+                payload = remaining;
+            }
+            Ok((Rlp::List(items), rest))
+        }
+    }
+}
+
+// `short` is the prefix minus its offset: a length below 56, or 55 plus the
+// size of a big-endian length that follows.
+fn split_payload(short: u8, bytes: &[u8]) -> Result<(&[u8], &[u8]), TrieError> {
+    let (length, bytes) = if short < 56 {
+        (short as usize, bytes)
+    } else {
+        let size = (short - 55) as usize;
+        if size > bytes.len() || size > 8 || bytes[0] == 0 {
+            return Err(TrieError::InvalidRlp);
+        }
+        let length = bytes[..size]
+            .iter()
+            .fold(0usize, |length, &b| (length << 8) | b as usize);
+        if length < 56 {
+            return Err(TrieError::InvalidRlp);
+        }
+        (length, &bytes[size..])
+    };
+    if length > bytes.len() {
+        return Err(TrieError::InvalidRlp);
+    }
+    Ok(bytes.split_at(length))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_encodes_strings() {
+        assert_eq!(encode_bytes(b""), vec![0x80]);
+        assert_eq!(encode_bytes(b"\x0f"), vec![0x0f]);
+        assert_eq!(encode_bytes(b"\x80"), vec![0x81, 0x80]);
+        assert_eq!(encode_bytes(b"dog"), b"\x83dog".to_vec());
+
+        let long = [b'a'; 56];
+        assert_eq!(encode_bytes(&long)[..2], [0xb8, 56]);
+        assert_eq!(encode_bytes(&long).len(), 58);
+    }
+
+    #[test]
+    fn test_encodes_lists() {
+        assert_eq!(encode_list(&[]), vec![0xc0]);
+        assert_eq!(
+            encode_list(&[encode_bytes(b"cat"), encode_bytes(b"dog")]),
+            b"\xc8\x83cat\x83dog".to_vec()
+        );
+    }
+
+    #[test]
+    fn test_encodes_integers() {
+        assert_eq!(encode_u64(0), vec![0x80]);
+        assert_eq!(encode_u64(15), vec![0x0f]);
+        assert_eq!(encode_u64(1024), vec![0x82, 0x04, 0x00]);
+    }
+
+    #[test]
+    fn test_decodes_what_it_encodes() {
+        let encoded = encode_list(&[
+            encode_bytes(b"cat"),
+            encode_list(&[encode_bytes(&[b'x'; 60])]),
+            encode_u64(1024),
+        ]);
+
+        let decoded = decode(&encoded).unwrap();
+
+        assert_eq!(
+            decoded,
+            Rlp::List(vec![
+                Rlp::Bytes(b"cat".to_vec()),
+                Rlp::List(vec![Rlp::Bytes(vec![b'x'; 60])]),
+                Rlp::Bytes(vec![0x04, 0x00]),
+            ])
+        );
+        match decoded {
+            Rlp::List(items) => assert_eq!(decode_u64(&items[2]), Ok(1024)),
+            Rlp::Bytes(_) => panic!("expected a list"),
+        }
+    }
+
+    #[test]
+    fn test_rejects_invalid_encodings() {
+        // Truncated, trailing bytes, a single small byte with a prefix, and a
+        // long length that fits in the short form.
+        assert_eq!(decode(b"\x83do"), Err(TrieError::InvalidRlp));
+        assert_eq!(decode(b"\x83dogs"), Err(TrieError::InvalidRlp));
+        assert_eq!(decode(b"\x81\x05"), Err(TrieError::InvalidRlp));
+        assert_eq!(decode(b"\xb8\x03dog"), Err(TrieError::InvalidRlp));
+        assert_eq!(decode(b""), Err(TrieError::InvalidRlp));
+        assert_eq!(
+            decode_u64(&Rlp::Bytes(vec![0, 1])),
+            Err(TrieError::InvalidRlp)
+        );
+    }
+
+    #[test]
+    fn test_limits_list_nesting() {
+        let nested =
+            |depth: usize| (0..depth).fold(encode_bytes(b""), |item, _| encode_list(&[item]));
+
+        assert!(decode(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(decode(&nested(MAX_DEPTH + 1)), Err(TrieError::InvalidRlp));
+    }
+}
@@ -1,3 +1,4 @@
+#[cfg(test)]
 fn select_validator(validators: Vec<i32>) -> i32 {
     // Create a random value between 0 and 1 and select the validador with the closest value.
     // Given [10, 20, 70] a random value of 0.15 should match 20 validator.
@@ -11,7 +11,7 @@ pub fn build_block(
     let prefix = build_prefix(difficulty);
     let mut nonce = 0;
     let mut block = Block::new(
-        previous_hash.clone(),
+        previous_hash,
         transactions.clone(),
         Some(Timestamp::new(0)),
         nonce,
@@ -20,7 +20,7 @@ pub fn build_block(
     while !block.hash().to_hex().starts_with(&prefix) {
         nonce = rand::random_range(0..u32::MAX);
         block = Block::new(
-            previous_hash.clone(),
+            previous_hash,
             transactions.clone(),
             Some(Timestamp::new(0)),
             nonce,
@@ -29,15 +29,16 @@ pub fn build_block(
     block.clone()
 }
 
+#[cfg(test)]
 fn proof_of_work(block: String, difficulty: usize) -> u32 {
     let prefix = build_prefix(difficulty);
     let mut nonce = 0;
 
-    let mut block_hash = hash::Hash::from_str(&format!("{}{}", block, nonce));
+    let mut block_hash = hash::Hash::digest_str(&format!("{}{}", block, nonce));
 
     while !block_hash.to_hex().starts_with(&prefix) {
         nonce += 1;
-        block_hash = hash::Hash::from_str(&format!("{}{}", block, nonce));
+        block_hash = hash::Hash::digest_str(&format!("{}{}", block, nonce));
     }
     nonce
 }
@@ -61,7 +62,7 @@ mod tests {
         let block = "Tx1".to_string();
         let difficulty = 4;
         let nonce = proof_of_work(block.clone(), difficulty);
-        let hash = hash::Hash::from_str(&format!("{}{}", block, nonce));
+        let hash = hash::Hash::digest_str(&format!("{}{}", block, nonce));
         assert!(hash.to_hex().starts_with("0000"));
     }
 
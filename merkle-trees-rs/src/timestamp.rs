@@ -1,6 +1,7 @@
 use std::time::{SystemTime, UNIX_EPOCH};
 
 #[derive(Debug, Clone, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Timestamp(u32);
 
 impl Timestamp {
@@ -21,6 +22,10 @@ impl Timestamp {
     pub fn to_bytes(&self) -> [u8; 4] {
         self.0.to_le_bytes()
     }
+
+    pub fn from_bytes(bytes: [u8; 4]) -> Self {
+        Self(u32::from_le_bytes(bytes))
+    }
 }
 
 impl PartialEq for Timestamp {
@@ -50,4 +55,10 @@ mod tests {
         let timestamp = Timestamp::new(0);
         assert_eq!(timestamp.to_bytes(), [0, 0, 0, 0]);
     }
+
+    #[test]
+    fn test_reads_bytes() {
+        let timestamp = Timestamp::new(1234567890);
+        assert_eq!(Timestamp::from_bytes(timestamp.to_bytes()), timestamp);
+    }
 }
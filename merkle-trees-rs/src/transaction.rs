@@ -1,7 +1,10 @@
+use crate::codec::{write_compact_size, DecodeError, Decoder};
 use crate::hash::Hash;
+use crate::hasher::{Hasher, Sha256};
 use crate::timestamp::Timestamp;
 
 #[derive(Debug, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Transaction {
     pub version: u32,
     pub from: String,
@@ -11,6 +14,12 @@ pub struct Transaction {
 }
 
 impl Transaction {
+    // Version 2 writes string lengths as CompactSize varints. Every other
+    // version writes them as fixed u32s, the original layout, so version 1
+    // ids are stable. Only versions 1 and 2 can be decoded.
+    pub const FIXED_VERSION: u32 = 1;
+    pub const COMPACT_VERSION: u32 = 2;
+
     pub fn new(
         version: u32,
         from: String,
@@ -45,12 +54,12 @@ impl Transaction {
 
         // From address (length + bytes)
         let from_bytes = self.from.as_bytes();
-        bytes.extend_from_slice(&(from_bytes.len() as u32).to_le_bytes());
+        self.write_length(&mut bytes, from_bytes.len());
         bytes.extend_from_slice(from_bytes);
 
         // To address (length + bytes)
         let to_bytes = self.to.as_bytes();
-        bytes.extend_from_slice(&(to_bytes.len() as u32).to_le_bytes());
+        self.write_length(&mut bytes, to_bytes.len());
         bytes.extend_from_slice(to_bytes);
 
         // Amount (8 bytes, little endian)
@@ -67,14 +76,73 @@ impl Transaction {
         bytes
     }
 
+    fn write_length(&self, bytes: &mut Vec<u8>, len: usize) {
+        if self.version == Self::COMPACT_VERSION {
+            write_compact_size(bytes, len as u64);
+        } else {
+            bytes.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+    }
+
+    // Fails unless `bytes` is exactly one transaction. None and a zero
+    // timestamp are both written as zeros, so a missing timestamp reads back
+    // as Some(0), with the same tx id.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, DecodeError> {
+        let mut decoder = Decoder::new(bytes);
+        let transaction = Self::decode(&mut decoder)?;
+        decoder.finish()?;
+        Ok(transaction)
+    }
+
+    pub(crate) fn decode(decoder: &mut Decoder) -> Result<Self, DecodeError> {
+        let version = decoder.u32()?;
+        let compact = match version {
+            Self::FIXED_VERSION => false,
+            Self::COMPACT_VERSION => true,
+            _ => return Err(DecodeError::UnsupportedVersion(version)),
+        };
+        let read_length = |decoder: &mut Decoder| {
+            if compact {
+                decoder.length()
+            } else {
+                Ok(decoder.u32()? as usize)
+            }
+        };
+        let from_len = read_length(decoder)?;
+        let from = decoder.string(from_len)?;
+        let to_len = read_length(decoder)?;
+        let to = decoder.string(to_len)?;
+        let amount = decoder.u64()?;
+        let timestamp = Some(Timestamp::new(decoder.u32()?));
+        Ok(Self::new(version, from, to, amount, timestamp))
+    }
+
     pub fn tx_id(&self) -> Hash {
-        Hash::from_bytes(&self.to_bytes())
+        self.tx_id_with::<Sha256>()
+    }
+
+    pub fn tx_id_with<H: Hasher>(&self) -> Hash {
+        H::hash(&self.to_bytes())
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use proptest::prelude::*;
+
+    fn arbitrary_transaction() -> impl Strategy<Value = Transaction> {
+        (
+            Transaction::FIXED_VERSION..=Transaction::COMPACT_VERSION,
+            ".{0,16}",
+            ".{0,16}",
+            any::<u64>(),
+            any::<u32>(),
+        )
+            .prop_map(|(version, from, to, amount, timestamp)| {
+                Transaction::new(version, from, to, amount, Some(Timestamp::new(timestamp)))
+            })
+    }
 
     #[test]
     fn test_creates_transaction() {
@@ -196,4 +264,167 @@ mod tests {
         // Genesis transaction should be different from regular transaction
         assert_ne!(genesis_tx.tx_id(), regular_tx.tx_id());
     }
+
+    #[test]
+    fn test_calculates_tx_id_with_hasher() {
+        use crate::hasher::Keccak256;
+
+        let tx = Transaction::new(
+            1,
+            "alice".to_string(),
+            "bob".to_string(),
+            1000000,
+            Some(Timestamp::new(1234567890)),
+        );
+
+        assert_eq!(tx.tx_id(), tx.tx_id_with::<Sha256>());
+        assert_eq!(
+            tx.tx_id_with::<Keccak256>(),
+            Keccak256::hash(&tx.to_bytes())
+        );
+        assert_ne!(tx.tx_id(), tx.tx_id_with::<Keccak256>());
+    }
+
+    #[test]
+    fn test_reads_transaction() {
+        let tx = Transaction::new(
+            1,
+            "alice".to_string(),
+            "bob".to_string(),
+            1000000,
+            Some(Timestamp::new(1234567890)),
+        );
+
+        assert_eq!(Transaction::from_bytes(&tx.to_bytes()), Ok(tx));
+    }
+
+    #[test]
+    fn test_reads_missing_timestamp_as_zero() {
+        let tx = Transaction::coinbase("miner".to_string(), 50, Some(Timestamp::new(0)));
+        assert_eq!(Transaction::from_bytes(&tx.to_bytes()), Ok(tx.clone()));
+
+        let untimed = Transaction::coinbase("miner".to_string(), 50, None);
+        let decoded = Transaction::from_bytes(&untimed.to_bytes()).unwrap();
+
+        assert_eq!(decoded, tx);
+        assert_eq!(decoded.tx_id(), untimed.tx_id());
+    }
+
+    #[test]
+    fn test_rejects_invalid_transactions() {
+        let tx = Transaction::new(1, "alice".to_string(), "bob".to_string(), 1, None);
+        let mut bytes = tx.to_bytes();
+
+        // A length running past the end, then a name that isn't UTF-8.
+        bytes[4..8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert_eq!(
+            Transaction::from_bytes(&bytes),
+            Err(DecodeError::UnexpectedEnd)
+        );
+        bytes[4..8].copy_from_slice(&5u32.to_le_bytes());
+        bytes[8] = 0xff;
+        assert_eq!(
+            Transaction::from_bytes(&bytes),
+            Err(DecodeError::InvalidUtf8)
+        );
+    }
+
+    #[test]
+    fn test_serializes_compact_lengths() {
+        let tx = Transaction::new(
+            Transaction::COMPACT_VERSION,
+            "alice".to_string(),
+            "b".repeat(300),
+            1000000,
+            Some(Timestamp::new(1234567890)),
+        );
+
+        let bytes = tx.to_bytes();
+
+        // version(4) + from_len(1) + from(5) + to_len(3) + to(300) + amount(8) + timestamp(4)
+        assert_eq!(bytes.len(), 325);
+        assert_eq!(bytes[4], 5);
+        assert_eq!(&bytes[10..13], &[0xfd, 0x2c, 0x01]);
+        assert_eq!(Transaction::from_bytes(&bytes), Ok(tx));
+    }
+
+    #[test]
+    fn test_keeps_version_1_tx_id() {
+        let tx = Transaction::new(
+            1,
+            "alice".to_string(),
+            "bob".to_string(),
+            1000000,
+            Some(Timestamp::new(1234567890)),
+        );
+        let compact = Transaction {
+            version: Transaction::COMPACT_VERSION,
+            ..tx.clone()
+        };
+
+        assert_eq!(
+            tx.tx_id(),
+            "c8e2da1eb1bb1247176c8ffc8ef047f682b2b7d4b863db2e72fceaf13a68388b"
+        );
+        assert_ne!(compact.tx_id(), tx.tx_id());
+    }
+
+    #[test]
+    fn test_rejects_unknown_versions() {
+        for version in [0, 3, u32::MAX] {
+            let tx = Transaction::new(version, "alice".to_string(), "bob".to_string(), 1, None);
+            let fixed = Transaction {
+                version: Transaction::FIXED_VERSION,
+                ..tx.clone()
+            };
+
+            assert_eq!(tx.to_bytes()[4..], fixed.to_bytes()[4..]);
+
+            assert_eq!(
+                Transaction::from_bytes(&tx.to_bytes()),
+                Err(DecodeError::UnsupportedVersion(version))
+            );
+        }
+    }
+
+    #[test]
+    fn test_rejects_non_minimal_lengths() {
+        let tx = Transaction::new(
+            Transaction::COMPACT_VERSION,
+            "a".to_string(),
+            String::new(),
+            1,
+            None,
+        );
+        let bytes = tx.to_bytes();
+        // The same length of 1, padded out to three bytes.
+        let padded = [&bytes[..4], &[0xfd, 0x01, 0x00], &bytes[5..]].concat();
+
+        assert_eq!(
+            Transaction::from_bytes(&padded),
+            Err(DecodeError::NonCanonicalCompactSize)
+        );
+    }
+
+    proptest! {
+        #[test]
+        fn test_reads_what_it_writes(tx in arbitrary_transaction()) {
+            prop_assert_eq!(Transaction::from_bytes(&tx.to_bytes()), Ok(tx));
+        }
+
+        #[test]
+        fn test_rejects_truncated_bytes(tx in arbitrary_transaction(), cut in any::<prop::sample::Index>()) {
+            let bytes = tx.to_bytes();
+            let len = cut.index(bytes.len());
+
+            prop_assert_eq!(Transaction::from_bytes(&bytes[..len]), Err(DecodeError::UnexpectedEnd));
+        }
+
+        #[test]
+        fn test_rejects_trailing_bytes(tx in arbitrary_transaction(), extra in prop::collection::vec(any::<u8>(), 1..16)) {
+            let bytes = [tx.to_bytes(), extra.clone()].concat();
+
+            prop_assert_eq!(Transaction::from_bytes(&bytes), Err(DecodeError::TrailingBytes(extra.len())));
+        }
+    }
 }